@@ -1,39 +1,220 @@
 #![no_std]
-use soroban_sdk::{contract, contractimpl, contracttype, Env};
+use soroban_sdk::{contract, contractimpl, contracttype, token, Address, Env};
+
+const MAX_BPS: i128 = 10_000;
 
 #[contracttype]
 pub enum DataKey {
     Balance,
+    Principal,
+    Vault,
+    Asset,
+    YieldRate,
+    LastAccrual,
+    Failures,
+}
+
+/// Misbehaviour to inject into the next calls from the vault.
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct FailureMode {
+    pub fail_deposit: bool,
+    pub fail_withdraw: bool,
+    pub fail_value: bool,
+    /// Part of every withdrawal, in basis points, that is never sent back
+    pub short_bps: u32,
 }
 
+/// Test strategy that takes custody of the vault's tokens.
+///
+/// The vault transfers tokens in before calling `deposit`; `withdraw` sends
+/// them back to the vault. The reported value accrues a fixed yield per
+/// second, which tests fund by minting tokens to the strategy the way an
+/// external protocol would pay it out. A strategy that was never
+/// initialised only keeps books and moves no tokens.
 #[contract]
 pub struct MockStrategy;
 
 #[contractimpl]
 impl MockStrategy {
+    /// Record the vault and asset this strategy serves
+    pub fn initialize(env: Env, vault: Address, asset: Address) {
+        if env.storage().instance().has(&DataKey::Vault) {
+            panic!("already initialized");
+        }
+        env.storage().instance().set(&DataKey::Vault, &vault);
+        env.storage().instance().set(&DataKey::Asset, &asset);
+        env.storage()
+            .instance()
+            .set(&DataKey::LastAccrual, &env.ledger().timestamp());
+    }
+
     pub fn balance(env: Env) -> i128 {
-        env.storage().instance().get(&DataKey::Balance).unwrap_or(0)
+        let stored: i128 = env.storage().instance().get(&DataKey::Balance).unwrap_or(0);
+        stored + Self::pending_yield(&env)
     }
 
     pub fn deposit(env: Env, amount: i128) {
-        let current: i128 = env.storage().instance().get(&DataKey::Balance).unwrap_or(0);
+        Self::require_vault(&env);
+        if Self::get_failure_mode(env.clone()).fail_deposit {
+            panic!("deposit failed");
+        }
+        let current = Self::accrue(&env);
         env.storage()
             .instance()
             .set(&DataKey::Balance, &(current + amount));
+        let principal = Self::principal(&env);
+        env.storage()
+            .instance()
+            .set(&DataKey::Principal, &(principal + amount));
     }
 
+    /// Release `amount` of value and send the tokens back to the vault.
+    /// Sends less when short withdrawals are injected or the strategy holds
+    /// fewer tokens than it reports.
     pub fn withdraw(env: Env, amount: i128) {
-        let current: i128 = env.storage().instance().get(&DataKey::Balance).unwrap_or(0);
+        Self::require_vault(&env);
+        let failures = Self::get_failure_mode(env.clone());
+        if failures.fail_withdraw {
+            panic!("withdraw failed");
+        }
+        let current = Self::accrue(&env);
+        env.storage()
+            .instance()
+            .set(&DataKey::Balance, &(current - amount));
+        let principal = Self::principal(&env);
+        env.storage()
+            .instance()
+            .set(&DataKey::Principal, &(principal - amount).max(0));
+
+        if let Some(vault) = env.storage().instance().get::<_, Address>(&DataKey::Vault) {
+            let token_client = token::Client::new(&env, &Self::asset(env.clone()));
+            let this = env.current_contract_address();
+            let owed = amount - amount * failures.short_bps as i128 / MAX_BPS;
+            let sent = owed.min(token_client.balance(&this));
+            if sent > 0 {
+                token_client.transfer(&this, &vault, &sent);
+            }
+        }
+    }
+
+    pub fn total_value(env: Env) -> i128 {
+        if Self::get_failure_mode(env.clone()).fail_value {
+            panic!("total_value failed");
+        }
+        Self::balance(env)
+    }
+
+    /// Report the change in value since the last harvest and take the
+    /// current value as the new principal
+    pub fn harvest(env: Env) -> (i128, i128) {
+        Self::require_vault(&env);
+        let value = Self::accrue(&env);
+        let principal = Self::principal(&env);
+        env.storage().instance().set(&DataKey::Principal, &value);
+        if value >= principal {
+            (value - principal, 0)
+        } else {
+            (0, principal - value)
+        }
+    }
+
+    pub fn max_withdraw(env: Env) -> i128 {
+        Self::balance(env).max(0)
+    }
+
+    pub fn asset(env: Env) -> Address {
+        env.storage()
+            .instance()
+            .get(&DataKey::Asset)
+            .expect("not initialized")
+    }
+
+    pub fn vault(env: Env) -> Address {
+        env.storage()
+            .instance()
+            .get(&DataKey::Vault)
+            .expect("not initialized")
+    }
+
+    /// Accrue `per_second` of yield on top of the reported value from now on
+    pub fn set_yield_rate(env: Env, per_second: i128) {
+        Self::accrue(&env);
+        env.storage()
+            .instance()
+            .set(&DataKey::YieldRate, &per_second);
+    }
+
+    /// Lose `amount` of value together with the tokens backing it
+    pub fn simulate_loss(env: Env, amount: i128) {
+        let current = Self::accrue(&env);
         env.storage()
             .instance()
             .set(&DataKey::Balance, &(current - amount));
+        if env.storage().instance().has(&DataKey::Asset) {
+            let token_client = token::Client::new(&env, &Self::asset(env.clone()));
+            let this = env.current_contract_address();
+            let burned = amount.min(token_client.balance(&this));
+            if burned > 0 {
+                token_client.burn(&this, &burned);
+            }
+        }
+    }
+
+    pub fn set_failure_mode(env: Env, failures: FailureMode) {
+        env.storage().instance().set(&DataKey::Failures, &failures);
+    }
+
+    pub fn get_failure_mode(env: Env) -> FailureMode {
+        env.storage()
+            .instance()
+            .get(&DataKey::Failures)
+            .unwrap_or_default()
     }
 
     /// Simulate price drift by directly modifying the balance
     /// This represents external factors affecting the strategy's value
     pub fn simulate_price_drift(env: Env, new_balance: i128) {
+        Self::accrue(&env);
         env.storage()
             .instance()
             .set(&DataKey::Balance, &new_balance);
     }
+
+    fn require_vault(env: &Env) {
+        if let Some(vault) = env.storage().instance().get::<_, Address>(&DataKey::Vault) {
+            vault.require_auth();
+        }
+    }
+
+    fn principal(env: &Env) -> i128 {
+        env.storage()
+            .instance()
+            .get(&DataKey::Principal)
+            .unwrap_or(0)
+    }
+
+    fn pending_yield(env: &Env) -> i128 {
+        let rate: i128 = env
+            .storage()
+            .instance()
+            .get(&DataKey::YieldRate)
+            .unwrap_or(0);
+        let last: u64 = env
+            .storage()
+            .instance()
+            .get(&DataKey::LastAccrual)
+            .unwrap_or(env.ledger().timestamp());
+        rate * (env.ledger().timestamp().saturating_sub(last) as i128)
+    }
+
+    /// Fold pending yield into the stored balance and return the new value
+    fn accrue(env: &Env) -> i128 {
+        let value = Self::balance(env.clone());
+        env.storage().instance().set(&DataKey::Balance, &value);
+        env.storage()
+            .instance()
+            .set(&DataKey::LastAccrual, &env.ledger().timestamp());
+        value
+    }
 }
@@ -1,9 +1,12 @@
 #![no_std]
 use soroban_sdk::{
     contract, contracterror, contractimpl, contracttype, symbol_short, token, Address, Env, Map,
-    Vec,
+    String, Vec,
 };
 
+pub mod strategy;
+use strategy::StrategyTraitClient;
+
 // ─────────────────────────────────────────────
 // Error types
 // ─────────────────────────────────────────────
@@ -32,8 +35,39 @@ pub enum Error {
     InvalidAllocationSum = 19,
     NegativeAllocation = 20,
     ZeroAddressStrategy = 21,
+    InvalidFeeRate = 22,
+    EpochClosed = 23,
+    EpochNotSettled = 24,
+    RateLimited = 25,
+    InvalidDebtRatio = 26,
+    KeeperDisabled = 27,
 }
 
+// ─────────────────────────────────────────────
+// Share math
+// ─────────────────────────────────────────────
+/// Virtual shares and assets added to the share price ratio. They make the
+/// classic first-depositor donation attack unprofitable without changing the
+/// 1:1 price of an empty vault: a donation is mostly captured by the virtual
+/// shares, and pushing a deposit to zero shares takes ~1000x its size.
+pub const VIRTUAL_SHARES: i128 = 1_000;
+pub const VIRTUAL_ASSETS: i128 = 1_000;
+
+/// Fee rates are expressed in basis points.
+pub const MAX_BPS: u32 = 10_000;
+
+/// Fixed-point scale of `price_per_share` and the high-water mark.
+pub const PRICE_PRECISION: i128 = 1_000_000_000;
+
+/// Upper bound on the annual management fee, in basis points.
+pub const MAX_MANAGEMENT_FEE_BPS: u32 = 500;
+/// Upper bound on the keeper bounty, in basis points of each payout.
+pub const MAX_KEEPER_BOUNTY_BPS: u32 = 100;
+/// Most empty queue slots, left behind by cancelled requests, that one call
+/// skips over; the next call carries on from where it stopped.
+pub const MAX_QUEUE_SKIP: u64 = 32;
+pub const SECONDS_PER_YEAR: u64 = 31_536_000;
+
 // ─────────────────────────────────────────────
 // Storage keys
 // ─────────────────────────────────────────────
@@ -43,7 +77,6 @@ pub enum DataKey {
     Admin,
     Asset,
     Oracle,
-    TotalAssets,
     TotalShares,
     Strategies,
     Treasury,
@@ -58,25 +91,180 @@ pub enum DataKey {
     OracleLastUpdate,
     MaxStaleness,
     TargetAllocations,
+    /// Target allocation of a strategy in emergency exit, kept out of
+    /// `TargetAllocations` until the strategy is re-enabled.
+    ExitedAllocation(Address),
     Guardians,
     Threshold,
     Proposals,
     NextProposalId,
     WithdrawQueueThreshold,
-    PendingWithdrawals,
+    Queue(QueueKey),
     StrategyHealth(Address),
     TimelockDuration,
+    Allowance(AllowanceDataKey),
+    ShareMetadata,
+    NavSnapshot,
+    NavMaxAge,
+    StrategyParams(Address),
+    AccruedFees,
+    HighWaterMark,
+    ManagementFee,
+    LastFeeAccrual,
+    EntryExitFees,
+    FeeExempt(Address),
+    LastDeposit(Address),
+    WithdrawalOrder,
+    LockedProfit,
+    ProfitUnlockPeriod,
+}
+
+/// Storage keys of the withdrawal queue. Requests live in persistent storage
+/// under their ID; `Head` is the oldest slot not yet processed and `Tail`
+/// the ID the next request will get, so the queue is the range
+/// `[Head, Tail)`. Cancelled requests leave an empty slot behind.
+#[contracttype]
+#[derive(Clone)]
+pub enum QueueKey {
+    Head,
+    Tail,
+    Request(u64),
+    User(Address),
+    CurrentEpoch,
+    Epoch(u64),
+    Reserved,
+    PullLiquidity,
+    Keeper,
+    LastKeeperRun,
+    Expiry,
+    ExpressFee,
+}
+
+// ─────────────────────────────────────────────
+// NAV snapshot struct
+// ─────────────────────────────────────────────
+/// Cached sum of strategy values. The vault's idle token balance is always
+/// read live, so only the cross-contract strategy calls are cached.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NavSnapshot {
+    pub strategy_assets: i128,
+    pub timestamp: u64,
+}
+
+/// Reported profit still being released into the share price. `amount` was
+/// locked at `reported_at` and unlocks linearly over the unlock period.
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct LockedProfit {
+    pub amount: i128,
+    pub reported_at: u64,
+}
+
+/// Rules for permissionless queue processing, which stays off until the
+/// admin sets them. Requests become due `delay` seconds after they were
+/// made; a keeper earns `bounty_bps` of every payout and keepers may
+/// pay out requests at most once every `min_interval` seconds.
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct KeeperConfig {
+    pub delay: u64,
+    pub bounty_bps: u32,
+    pub min_interval: u64,
+}
+
+/// What happens to a request once it has waited longer than the maximum
+/// queue age.
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ExpiryPolicy {
+    /// Pay it out ahead of the keeper delay.
+    Process,
+    /// Cancel it and return the escrowed shares. Requests whose epoch has
+    /// already been settled are paid out instead.
+    Cancel,
+}
+
+/// Maximum queue age in seconds (0 disables expiry) and the policy applied
+/// to requests older than that.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct QueueExpiry {
+    pub max_age: u64,
+    pub policy: ExpiryPolicy,
+}
+
+// ─────────────────────────────────────────────
+// Entry / exit fee struct
+// ─────────────────────────────────────────────
+/// Fees charged on the assets moving in and out of the vault, in basis
+/// points. The withdrawal fee is waived once an account has held its shares
+/// for `min_holding_period` seconds (0 disables the waiver).
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct EntryExitFees {
+    pub deposit_bps: u32,
+    pub withdraw_bps: u32,
+    pub min_holding_period: u64,
+}
+
+// ─────────────────────────────────────────────
+// Share token (SEP-41) structs
+// ─────────────────────────────────────────────
+#[contracttype]
+#[derive(Clone)]
+pub struct AllowanceDataKey {
+    pub from: Address,
+    pub spender: Address,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AllowanceValue {
+    pub amount: i128,
+    pub expiration_ledger: u32,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ShareMetadata {
+    pub name: String,
+    pub symbol: String,
 }
 
 // ─────────────────────────────────────────────
 // Queued withdrawal struct
 // ─────────────────────────────────────────────
+/// A withdrawal waiting in the queue. `shares` are held in escrow by the
+/// vault; `assets` is their value when the request was made and is kept for
+/// reference only. `user` made the request; `owner` currently holds the
+/// claim and receives the payout.
+///
+/// Pricing policy: requests are batched into redemption epochs. When an
+/// epoch is closed every request in it is settled at the same price per
+/// share, whatever the order in which the requests arrived.
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct QueuedWithdrawal {
+    pub id: u64,
     pub user: Address,
+    pub owner: Address,
     pub shares: i128,
+    pub assets: i128,
     pub timestamp: u64,
+    pub epoch: u64,
+}
+
+/// Totals of a redemption epoch. While open, `shares` grows with each
+/// request. On close the escrowed shares are burned and `assets` is set
+/// aside for the claimants; both then shrink as requests are paid out.
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RedemptionEpoch {
+    pub shares: i128,
+    pub assets: i128,
+    pub settled: bool,
+    pub settled_at: u64,
 }
 
 #[contracttype]
@@ -85,6 +273,7 @@ pub enum ActionType {
     SetPaused(bool),
     AddStrategy(Address),
     Rebalance(u32),
+    ReenableStrategy(Address),
 }
 
 #[contracttype]
@@ -110,41 +299,22 @@ pub struct StrategyHealth {
 }
 
 // ─────────────────────────────────────────────
-// Strategy cross-contract client
+// Strategy params struct
 // ─────────────────────────────────────────────
-pub struct StrategyClient<'a> {
-    env: &'a Env,
-    address: Address,
-}
-
-impl<'a> StrategyClient<'a> {
-    pub fn new(env: &'a Env, address: Address) -> Self {
-        Self { env, address }
-    }
-
-    pub fn deposit(&self, amount: i128) {
-        self.env.invoke_contract::<()>(
-            &self.address,
-            &soroban_sdk::Symbol::new(self.env, "deposit"),
-            soroban_sdk::vec![self.env, soroban_sdk::IntoVal::into_val(&amount, self.env)],
-        );
-    }
-
-    pub fn withdraw(&self, amount: i128) {
-        self.env.invoke_contract::<()>(
-            &self.address,
-            &soroban_sdk::Symbol::new(self.env, "withdraw"),
-            soroban_sdk::vec![self.env, soroban_sdk::IntoVal::into_val(&amount, self.env)],
-        );
-    }
-
-    pub fn balance(&self) -> i128 {
-        self.env.invoke_contract::<i128>(
-            &self.address,
-            &soroban_sdk::Symbol::new(self.env, "balance"),
-            soroban_sdk::vec![self.env],
-        )
-    }
+/// Debt limits and lifetime results for one strategy. A strategy may hold at
+/// most `debt_ratio` of the vault's assets and never more than `max_debt`.
+/// A strategy in `emergency_exit` gets no allocations and is left out of the
+/// vault's NAV until governance re-enables it.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StrategyParams {
+    pub debt_ratio: u32,
+    pub max_debt: i128,
+    pub current_debt: i128,
+    pub total_gain: i128,
+    pub total_loss: i128,
+    pub last_report: u64,
+    pub emergency_exit: bool,
 }
 
 // ─────────────────────────────────────────────
@@ -282,6 +452,9 @@ impl VolatilityShield {
             ActionType::Rebalance(max_slippage) => {
                 Self::internal_rebalance(env, *max_slippage)?;
             }
+            ActionType::ReenableStrategy(strategy) => {
+                Self::internal_reenable_strategy(env, strategy)?;
+            }
         }
 
         // Emit TimelockExecuted event
@@ -302,7 +475,7 @@ impl VolatilityShield {
         }
 
         let now = env.ledger().timestamp();
-        let elapsed = now.checked_sub(proposed_at).unwrap_or(0);
+        let elapsed = now.saturating_sub(proposed_at);
         
         if elapsed < timelock_duration {
             return Err(Error::TimelockNotElapsed);
@@ -313,6 +486,7 @@ impl VolatilityShield {
 
     // ── Initialization ────────────────────────
     /// Must be called once. Stores roles and configuration.
+    #[allow(clippy::too_many_arguments)]
     pub fn init(
         env: Env,
         admin: Address,
@@ -339,7 +513,6 @@ impl VolatilityShield {
         env.storage().instance().set(&DataKey::Token, &asset);
 
         // Initialize vault state to zero
-        env.storage().instance().set(&DataKey::TotalAssets, &0_i128);
         env.storage().instance().set(&DataKey::TotalShares, &0_i128);
         env.storage()
             .instance()
@@ -360,25 +533,65 @@ impl VolatilityShield {
 
     // ── Deposit ───────────────────────────────
     pub fn deposit(env: Env, from: Address, amount: i128) {
+        Self::deposit_for(env, from.clone(), from, amount);
+    }
+
+    /// Deposit `amount` assets pulled from `from` and credit the shares to `receiver`.
+    pub fn deposit_for(env: Env, from: Address, receiver: Address, amount: i128) {
         Self::check_version(&env, 1);
         Self::assert_not_paused(&env);
         if amount <= 0 {
             panic!("deposit amount must be positive");
         }
         from.require_auth();
+        Self::accrue_management_fee(&env);
+
+        let fee = Self::fee_on_total(amount, Self::entry_fee_bps(&env, &from));
+        let shares_to_mint = Self::convert_to_shares(env.clone(), amount - fee);
+        if shares_to_mint == 0 {
+            panic!("deposit would mint zero shares");
+        }
+        Self::execute_deposit(&env, &from, &receiver, amount - fee, fee, shares_to_mint);
+    }
+
+    /// Mint exactly `shares` shares to `receiver`, pulling the required assets
+    /// (rounded up, in favour of the vault) from `from`. Returns the assets paid.
+    pub fn mint(env: Env, from: Address, receiver: Address, shares: i128) -> i128 {
+        Self::check_version(&env, 1);
+        Self::assert_not_paused(&env);
+        if shares <= 0 {
+            panic!("shares to mint must be positive");
+        }
+        from.require_auth();
+        Self::accrue_management_fee(&env);
+
+        let assets = Self::assets_for_shares(&env, shares, true);
+        let fee = Self::fee_on_raw(assets, Self::entry_fee_bps(&env, &from));
+        Self::execute_deposit(&env, &from, &receiver, assets, fee, shares);
+        assets + fee
+    }
+
+    /// Pull `amount` assets into the vault plus `fee` to the treasury, and
+    /// mint `shares_to_mint` to `receiver`.
+    fn execute_deposit(
+        env: &Env,
+        from: &Address,
+        receiver: &Address,
+        amount: i128,
+        fee: i128,
+        shares_to_mint: i128,
+    ) {
+        let total_assets = Self::total_assets(env);
 
         let token: Address = env
             .storage()
             .instance()
             .get(&DataKey::Token)
             .expect("Token not initialized");
-        token::Client::new(&env, &token).transfer(&from, &env.current_contract_address(), &amount);
-
-        let shares_to_mint = Self::convert_to_shares(env.clone(), amount);
-
-        let balance_key = DataKey::Balance(from.clone());
-        let current_balance: i128 = env.storage().persistent().get(&balance_key).unwrap_or(0);
+        token::Client::new(env, &token).transfer(from, &env.current_contract_address(), &amount);
+        Self::pay_asset_fee(env, from, fee, symbol_short!("entry"));
 
+        let current_balance = Self::balance(env.clone(), receiver.clone());
         let new_user_balance = current_balance.checked_add(shares_to_mint).unwrap();
 
         // --- Deposit Caps Validation ---
@@ -393,7 +606,6 @@ impl VolatilityShield {
             panic!("DepositCapExceeded: per-user deposit cap exceeded");
         }
 
-        let total_assets = Self::total_assets(&env);
         let new_total_assets = total_assets.checked_add(amount).unwrap();
 
         let max_total_assets: i128 = env
@@ -408,19 +620,11 @@ impl VolatilityShield {
         }
         // -------------------------------
 
-        env.storage()
-            .persistent()
-            .set(&balance_key, &new_user_balance);
-
-        let total_shares = Self::total_shares(&env);
-        Self::set_total_shares(
-            env.clone(),
-            total_shares.checked_add(shares_to_mint).unwrap(),
-        );
-        Self::set_total_assets(env.clone(), total_assets.checked_add(amount).unwrap());
+        Self::mint_shares(env, receiver, shares_to_mint);
+        Self::record_deposit_time(env, receiver, shares_to_mint);
 
         env.events()
-            .publish((symbol_short!("Deposit"), from.clone()), amount);
+            .publish((symbol_short!("Deposit"), from.clone()), amount + fee);
     }
 
     // ── Withdraw ──────────────────────────────
@@ -431,81 +635,195 @@ impl VolatilityShield {
             panic!("shares to withdraw must be positive");
         }
         from.require_auth();
+        Self::accrue_management_fee(&env);
 
-        let balance_key = DataKey::Balance(from.clone());
-        let current_balance: i128 = env.storage().persistent().get(&balance_key).unwrap_or(0);
-
+        let current_balance = Self::balance(env.clone(), from.clone());
         if current_balance < shares {
             panic!("insufficient shares for withdrawal");
         }
 
         let assets_to_withdraw = Self::convert_to_assets(env.clone(), shares);
+        Self::assert_withdraw_cap(&env, assets_to_withdraw);
+
+        // Check if withdrawal exceeds queue threshold
+        let queue_threshold: i128 = env.storage().instance().get(&DataKey::WithdrawQueueThreshold).unwrap_or(i128::MAX);
+        if assets_to_withdraw > queue_threshold {
+            // Queue the withdrawal instead of processing immediately
+            Self::queue_withdraw(env, from, shares);
+            return;
+        }
+
+        let fee = Self::fee_on_total(assets_to_withdraw, Self::exit_fee_bps(&env, &from));
+        Self::execute_withdraw(&env, &from, &from, shares, assets_to_withdraw - fee, fee);
+    }
+
+    /// Burn `shares` from `owner` and pay the assets to `receiver`.
+    /// Unlike `withdraw`, amounts above the queue threshold are never
+    /// queued: they take the express lane and pay the express exit fee, or
+    /// are rejected if the lane is disabled. Returns the assets paid.
+    pub fn redeem(env: Env, owner: Address, receiver: Address, shares: i128) -> i128 {
+        Self::check_version(&env, 1);
+        Self::assert_not_paused(&env);
+        if shares <= 0 {
+            panic!("shares to withdraw must be positive");
+        }
+        owner.require_auth();
+        Self::accrue_management_fee(&env);
+
+        let assets = Self::assets_for_shares(&env, shares, false);
+        Self::assert_instant_withdraw(&env, assets);
+        let penalty = Self::express_fee_on(&env, assets);
+        Self::record_express_exit(&env, &owner, shares, penalty);
+        let fee = Self::fee_on_total(assets - penalty, Self::exit_fee_bps(&env, &owner));
+        Self::execute_withdraw(&env, &owner, &receiver, shares, assets - penalty - fee, fee)
+    }
 
-        // --- Withdraw Caps Validation ---
+    /// Withdraw exactly `assets` to `receiver`, burning the required shares
+    /// (rounded up, in favour of the vault) from `owner`. Like `redeem`,
+    /// amounts above the queue threshold use the express lane. Returns the
+    /// shares burned.
+    pub fn withdraw_assets(env: Env, owner: Address, receiver: Address, assets: i128) -> i128 {
+        Self::check_version(&env, 1);
+        Self::assert_not_paused(&env);
+        if assets <= 0 {
+            panic!("assets to withdraw must be positive");
+        }
+        owner.require_auth();
+        Self::accrue_management_fee(&env);
+
+        let fee = Self::fee_on_raw(assets, Self::exit_fee_bps(&env, &owner));
+        let gross = Self::gross_up_express_fee(&env, assets + fee);
+        let shares = Self::shares_for_assets(&env, gross, true);
+        Self::assert_instant_withdraw(&env, gross);
+        Self::record_express_exit(&env, &owner, shares, gross - assets - fee);
+        if Self::execute_withdraw(&env, &owner, &receiver, shares, assets, fee) < assets {
+            panic!("withdrawal would realise a strategy loss");
+        }
+        shares
+    }
+
+    fn assert_withdraw_cap(env: &Env, assets: i128) {
         let max_withdraw_per_tx: i128 = env
             .storage()
             .instance()
             .get(&DataKey::MaxWithdrawPerTx)
             .unwrap_or(i128::MAX);
-        if assets_to_withdraw > max_withdraw_per_tx {
+        if assets > max_withdraw_per_tx {
             env.events().publish(
                 (symbol_short!("Cap"), symbol_short!("withdraw")),
-                assets_to_withdraw,
+                assets,
             );
             panic!("WithdrawalCapExceeded: per-tx withdrawal cap exceeded");
         }
-        // --------------------------------
+    }
 
-        // Check if withdrawal exceeds queue threshold
-        let queue_threshold: i128 = env.storage().instance().get(&DataKey::WithdrawQueueThreshold).unwrap_or(i128::MAX);
-        if assets_to_withdraw > queue_threshold {
-            // Queue the withdrawal instead of processing immediately
-            Self::queue_withdraw(env, from, shares);
-            return;
+    fn assert_instant_withdraw(env: &Env, assets: i128) {
+        Self::assert_withdraw_cap(env, assets);
+        if assets > Self::get_withdraw_queue_threshold(env.clone())
+            && Self::get_express_exit_fee(env.clone()) == 0
+        {
+            panic!("withdrawal exceeds queue threshold");
         }
+    }
 
-        let total_shares = Self::total_shares(&env);
-        let total_assets = Self::total_assets(&env);
+    // ── Express Lane ──────────────────────────
+    /// Set the fee, in basis points, for exiting instantly with an amount
+    /// above the queue threshold. The fee stays in the vault for the
+    /// remaining holders. 0 disables the express lane.
+    pub fn set_express_exit_fee(env: Env, fee_bps: u32) -> Result<(), Error> {
+        Self::require_admin(&env);
+        if fee_bps >= MAX_BPS {
+            return Err(Error::InvalidFeeRate);
+        }
+        env.storage()
+            .instance()
+            .set(&DataKey::Queue(QueueKey::ExpressFee), &fee_bps);
+        env.events().publish((symbol_short!("Express"),), fee_bps);
+        Ok(())
+    }
 
-        Self::set_total_shares(env.clone(), total_shares.checked_sub(shares).unwrap());
-        Self::set_total_assets(
-            env.clone(),
-            total_assets.checked_sub(assets_to_withdraw).unwrap(),
-        );
-        env.storage().persistent().set(
-            &balance_key,
-            &(current_balance.checked_sub(shares).unwrap()),
-        );
+    pub fn get_express_exit_fee(env: Env) -> u32 {
+        env.storage()
+            .instance()
+            .get(&DataKey::Queue(QueueKey::ExpressFee))
+            .unwrap_or(0)
+    }
+
+    /// Express fee included in a gross withdrawal of `assets`; zero at or
+    /// below the queue threshold.
+    fn express_fee_on(env: &Env, assets: i128) -> i128 {
+        if assets <= Self::get_withdraw_queue_threshold(env.clone()) {
+            return 0;
+        }
+        Self::fee_on_total(assets, Self::get_express_exit_fee(env.clone()))
+    }
+
+    /// Gross amount that leaves `net` once the express fee is taken.
+    fn gross_up_express_fee(env: &Env, net: i128) -> i128 {
+        if net <= Self::get_withdraw_queue_threshold(env.clone()) {
+            return net;
+        }
+        net + Self::fee_on_raw(net, Self::get_express_exit_fee(env.clone()))
+    }
+
+    /// The express fee is simply not paid out, which leaves it in the vault;
+    /// only an event records it.
+    fn record_express_exit(env: &Env, owner: &Address, shares: i128, penalty: i128) {
+        if penalty > 0 {
+            env.events()
+                .publish((symbol_short!("express"), owner.clone()), (shares, penalty));
+        }
+    }
+
+    /// Burn `shares` from `owner`, pay `assets` to `receiver` and `fee` to
+    /// the treasury. Idle assets are used first and the rest is pulled from
+    /// strategies in withdrawal order; any loss a strategy realises while
+    /// doing so is deducted from `assets`. Returns the assets paid.
+    fn execute_withdraw(
+        env: &Env,
+        owner: &Address,
+        receiver: &Address,
+        shares: i128,
+        assets: i128,
+        fee: i128,
+    ) -> i128 {
+        Self::burn_shares(env, owner, shares);
 
         let token: Address = env
             .storage()
             .instance()
             .get(&DataKey::Token)
             .expect("Token not initialized");
-        token::Client::new(&env, &token).transfer(
-            &env.current_contract_address(),
-            &from,
-            &assets_to_withdraw,
-        );
+        let token_client = token::Client::new(env, &token);
+        let vault = env.current_contract_address();
+        let idle = token_client.balance(&vault) - Self::reserved_assets(env.clone());
+        let (received, loss) = Self::pull_from_strategies(env, assets + fee - idle);
+        if idle + received + loss < assets + fee {
+            panic!("insufficient liquidity for withdrawal");
+        }
+
+        let paid = assets - loss.min(assets);
+        token_client.transfer(&vault, receiver, &paid);
+        Self::pay_asset_fee(env, &vault, fee, symbol_short!("exit"));
 
         env.events()
-            .publish((symbol_short!("withdraw"), from.clone()), shares);
+            .publish((symbol_short!("withdraw"), owner.clone()), shares);
+        paid
     }
 
 
     // ── Withdrawal Queue ───────────────────────
-    /// Queue a withdrawal request for processing later.
+    /// Queue a withdrawal request for processing later and return its ID.
     /// This is called automatically by withdraw() when the amount exceeds the threshold.
-    pub fn queue_withdraw(env: Env, from: Address, shares: i128) {
+    /// A user may have several requests outstanding at once.
+    pub fn queue_withdraw(env: Env, from: Address, shares: i128) -> u64 {
         Self::assert_not_paused(&env);
         if shares <= 0 {
             panic!("shares to queue must be positive");
         }
         from.require_auth();
 
-        let balance_key = DataKey::Balance(from.clone());
-        let current_balance: i128 = env.storage().persistent().get(&balance_key).unwrap_or(0);
-
+        let current_balance = Self::balance(env.clone(), from.clone());
         if current_balance < shares {
             panic!("insufficient shares for withdrawal");
         }
@@ -521,24 +839,39 @@ impl VolatilityShield {
             panic!("withdrawal amount does not exceed queue threshold");
         }
 
+        // Escrow the shares until the request is processed or cancelled
+        Self::move_shares(&env, &from, &env.current_contract_address(), shares);
+
+        let id = Self::queue_tail(&env);
+        env.storage()
+            .instance()
+            .set(&DataKey::Queue(QueueKey::Tail), &(id + 1));
+
+        let epoch_id = Self::current_epoch(env.clone());
+        let mut epoch = Self::get_epoch(env.clone(), epoch_id);
+        epoch.shares = epoch.shares.checked_add(shares).unwrap();
+        Self::set_epoch(&env, epoch_id, &epoch);
+
         // Create queued withdrawal entry
         let queued_withdrawal = QueuedWithdrawal {
+            id,
             user: from.clone(),
+            owner: from.clone(),
             shares,
+            assets: assets_to_withdraw,
             timestamp: env.ledger().timestamp(),
+            epoch: epoch_id,
         };
+        env.storage()
+            .persistent()
+            .set(&DataKey::Queue(QueueKey::Request(id)), &queued_withdrawal);
 
-        // Add to pending withdrawals queue
-        let mut pending_withdrawals: Vec<QueuedWithdrawal> = env.storage().instance()
-            .get(&DataKey::PendingWithdrawals)
-            .unwrap_or(Vec::new(&env));
-        
-        pending_withdrawals.push_back(queued_withdrawal);
-        env.storage().instance().set(&DataKey::PendingWithdrawals, &pending_withdrawals);
+        Self::index_withdrawal(&env, &from, id);
 
         // Emit WithdrawQueued event
         env.events()
-            .publish((symbol_short!("WithdrawQ"), from.clone()), shares);
+            .publish((symbol_short!("WithdrawQ"), from.clone()), (id, shares));
+        id
     }
 
     // ── Withdraw Queue Management ─────────────────────
@@ -552,117 +885,666 @@ impl VolatilityShield {
         env.events().publish((symbol_short!("QueueThr"),), threshold);
     }
 
-    /// Process queued withdrawals (admin only)
+    /// Process queued withdrawals in FIFO order (admin only).
+    /// If the oldest request belongs to the open epoch, that epoch is closed
+    /// first. When the vault runs out of liquidity the request at the head
+    /// is partly filled and processing stops. Only the requests processed
+    /// and at most `MAX_QUEUE_SKIP` empty slots are read, so the cost does
+    /// not depend on the queue length. Returns the number of requests paid
+    /// out in full.
     pub fn process_queued_withdrawals(env: Env, limit: u32) -> u32 {
         Self::require_admin(&env);
+        Self::process_queue(&env, limit, 0, None).0
+    }
+
+    /// Permissionless queue processing. Once the keeper config is set,
+    /// anyone may pay out requests older than the keeper delay and earn the
+    /// keeper bounty on each payout. A keeper closes the open epoch only
+    /// once its oldest request is due. Returns the number of requests paid
+    /// out in full.
+    pub fn process_due_withdrawals(env: Env, keeper: Address, limit: u32) -> Result<u32, Error> {
+        keeper.require_auth();
+        Self::assert_not_paused(&env);
         
-        let pending_withdrawals: Vec<QueuedWithdrawal> = env.storage().instance()
-            .get(&DataKey::PendingWithdrawals)
-            .unwrap_or(Vec::new(&env));
+        let config = Self::get_keeper_config(env.clone()).ok_or(Error::KeeperDisabled)?;
+        let now = env.ledger().timestamp();
+        let last_run: Option<u64> = env
+            .storage()
+            .instance()
+            .get(&DataKey::Queue(QueueKey::LastKeeperRun));
+        if let Some(last_run) = last_run {
+            if now < last_run.saturating_add(config.min_interval) {
+                return Err(Error::RateLimited);
+            }
+        }
         
-        let mut processed = 0;
-        let mut remaining_withdrawals = Vec::new(&env);
+        let (processed, paid) =
+            Self::process_queue(&env, limit, config.delay, Some((&keeper, config.bounty_bps)));
+        // Only a run that paid something out, even a partial fill, counts
+        // towards the rate limit
+        if paid > 0 {
+            env.storage()
+                .instance()
+                .set(&DataKey::Queue(QueueKey::LastKeeperRun), &now);
+        }
         
-        let mut total_shares = Self::total_shares(&env);
-        let mut total_assets = Self::total_assets(&env);
+        env.events()
+            .publish((symbol_short!("keeper"), keeper), processed);
+        Ok(processed)
+    }
 
-        let token: Address = env.storage().instance().get(&DataKey::Token).expect("Token not initialized");
-        let token_client = token::Client::new(&env, &token);
+    pub fn set_keeper_config(env: Env, delay: u64, bounty_bps: u32, min_interval: u64) -> Result<(), Error> {
+        Self::require_admin(&env);
+        if bounty_bps > MAX_KEEPER_BOUNTY_BPS {
+            return Err(Error::InvalidFeeRate);
+        }
+        let config = KeeperConfig {
+            delay,
+            bounty_bps,
+            min_interval,
+        };
+        env.storage()
+            .instance()
+            .set(&DataKey::Queue(QueueKey::Keeper), &config);
+        env.events()
+            .publish((symbol_short!("KeeperCfg"),), (delay, bounty_bps, min_interval));
+        Ok(())
+    }
 
-        for queued_withdrawal in pending_withdrawals.iter() {
-            if processed >= limit {
-                remaining_withdrawals.push_back(queued_withdrawal.clone());
-                continue;
-            }
-            
-            // Process the withdrawal
-            let assets_to_withdraw = Self::convert_to_assets(env.clone(), queued_withdrawal.shares);
-            
-            total_shares = total_shares.checked_sub(queued_withdrawal.shares).unwrap();
-            total_assets = total_assets.checked_sub(assets_to_withdraw).unwrap();
-            
-            token_client.transfer(
-                &env.current_contract_address(),
-                &queued_withdrawal.user,
-                &assets_to_withdraw,
-            );
-            
-            env.events()
-                .publish((symbol_short!("WithdrawP"), queued_withdrawal.user.clone()), queued_withdrawal.shares);
-            
-            processed += 1;
+    /// Keeper rules, or `None` while permissionless processing is disabled.
+    pub fn get_keeper_config(env: Env) -> Option<KeeperConfig> {
+        env.storage()
+            .instance()
+            .get(&DataKey::Queue(QueueKey::Keeper))
+    }
+
+    pub fn set_queue_expiry(env: Env, max_age: u64, policy: ExpiryPolicy) {
+        Self::require_admin(&env);
+        let expiry = QueueExpiry { max_age, policy };
+        env.storage()
+            .instance()
+            .set(&DataKey::Queue(QueueKey::Expiry), &expiry);
+        env.events()
+            .publish((symbol_short!("QueueExp"),), (max_age, policy));
+    }
+
+    pub fn get_queue_expiry(env: Env) -> QueueExpiry {
+        env.storage()
+            .instance()
+            .get(&DataKey::Queue(QueueKey::Expiry))
+            .unwrap_or(QueueExpiry {
+                max_age: 0,
+                policy: ExpiryPolicy::Process,
+            })
+    }
+
+    /// Apply the expiry policy to up to `limit` requests older than the
+    /// maximum queue age. Anyone may call this. Requests are queued in time
+    /// order, so expired requests are always at the head. Returns the number
+    /// of requests swept.
+    pub fn sweep_expired_withdrawals(env: Env, limit: u32) -> u32 {
+        Self::assert_not_paused(&env);
+        let expiry = Self::get_queue_expiry(env.clone());
+        if expiry.max_age == 0 {
+            return 0;
         }
         
-        // Update totals
-        Self::set_total_shares(env.clone(), total_shares);
-        Self::set_total_assets(env.clone(), total_assets);
+        let tail = Self::queue_tail(&env);
+        let mut head = Self::queue_head(&env);
+        let mut swept = 0;
+        let mut skipped = 0;
         
-        // Update remaining withdrawals
-        env.storage().instance().set(&DataKey::PendingWithdrawals, &remaining_withdrawals);
+        while swept < limit && head < tail {
+            let Some(w) = Self::get_withdrawal(env.clone(), head) else {
+                if skipped == MAX_QUEUE_SKIP {
+                    break;
+                }
+                skipped += 1;
+                head = Self::set_queue_head(&env, head + 1);
+                continue;
+            };
+            if env.ledger().timestamp().saturating_sub(w.timestamp) < expiry.max_age {
+                break;
+            }
+            let open = w.epoch == Self::current_epoch(env.clone());
+            if expiry.policy == ExpiryPolicy::Cancel && open {
+                Self::remove_queued_withdrawal(&env, w.clone()).unwrap();
+                env.events().publish(
+                    (symbol_short!("expired"), w.owner),
+                    (w.id, symbol_short!("cancel")),
+                );
+            } else {
+                if open {
+                    Self::settle_epoch(&env);
+                }
+                Self::pay_withdrawal(&env, &w, None);
+                env.events().publish(
+                    (symbol_short!("expired"), w.owner),
+                    (w.id, symbol_short!("process")),
+                );
+                if Self::get_withdrawal(env.clone(), head).is_some() {
+                    // Only partly filled: no liquidity left
+                    break;
+                }
+            }
+            swept += 1;
+            head = Self::set_queue_head(&env, head + 1);
+        }
         
-        processed
+        swept
     }
 
-    /// Cancel a queued withdrawal
-    pub fn cancel_queued_withdrawal(env: Env, from: Address) -> Result<(), Error> {
-        from.require_auth();
-        
-        let mut pending_withdrawals: Vec<QueuedWithdrawal> = env.storage().instance()
-            .get(&DataKey::PendingWithdrawals)
-            .unwrap_or(Vec::new(&env));
-        
-        let mut found_index: Option<u32> = None;
-        let mut found_withdrawal: Option<QueuedWithdrawal> = None;
-        
-        for i in 0..pending_withdrawals.len() {
-            let w = pending_withdrawals.get(i).unwrap();
-            if w.user == from {
-                found_index = Some(i);
-                found_withdrawal = Some(w);
+    /// Pay out up to `limit` requests from the head of the queue, stopping
+    /// at the first one younger than `min_age` seconds or after skipping
+    /// `MAX_QUEUE_SKIP` empty slots. Reaching a due request of the open
+    /// epoch closes that epoch. Returns the number of requests paid in full and the
+    /// assets paid out, partial fills included.
+    fn process_queue(
+        env: &Env,
+        limit: u32,
+        min_age: u64,
+        bounty: Option<(&Address, u32)>,
+    ) -> (u32, i128) {
+        let tail = Self::queue_tail(env);
+        let mut head = Self::queue_head(env);
+        let mut processed = 0;
+        let mut paid: i128 = 0;
+        let mut skipped = 0;
+
+        while processed < limit && head < tail {
+            let Some(queued_withdrawal) = Self::get_withdrawal(env.clone(), head) else {
+                if skipped == MAX_QUEUE_SKIP {
+                    break;
+                }
+                skipped += 1;
+                head = Self::set_queue_head(env, head + 1);
+                continue;
+            };
+            if env.ledger().timestamp().saturating_sub(queued_withdrawal.timestamp) < min_age {
+                break;
+            }
+            if queued_withdrawal.epoch == Self::current_epoch(env.clone()) {
+                Self::settle_epoch(env);
+            }
+            paid = paid
+                .checked_add(Self::pay_withdrawal(env, &queued_withdrawal, bounty))
+                .unwrap();
+            if Self::get_withdrawal(env.clone(), head).is_some() {
+                // Only partly filled: no liquidity left for later requests
                 break;
             }
+            
+            processed += 1;
+            head = Self::set_queue_head(env, head + 1);
         }
         
-        let index = found_index.ok_or(Error::WithdrawalNotFound)?;
-        let w = found_withdrawal.unwrap();
-        
-        pending_withdrawals.remove(index);
-        
-        // Return shares to user balance
-        let balance_key = DataKey::Balance(from.clone());
-        let current_balance: i128 = env.storage().persistent().get(&balance_key).unwrap_or(0);
-        env.storage().persistent().set(&balance_key, &(current_balance + w.shares));
-        
-        env.storage().instance().set(&DataKey::PendingWithdrawals, &pending_withdrawals);
-        
-        env.events().publish(
-            (symbol_short!("WdrwCncl"),),
-            (from, w.shares),
-        );
-        
-        Ok(())
+        (processed, paid)
     }
 
-    /// Get the current withdrawal queue threshold
-    pub fn get_withdraw_queue_threshold(env: Env) -> i128 {
-        env.storage().instance()
-            .get(&DataKey::WithdrawQueueThreshold)
-            .unwrap_or(i128::MAX)
+    // ── Redemption Epochs ─────────────────────
+    /// Close the open redemption epoch (admin only). Liquidity is raised
+    /// from strategies, every request in the epoch is priced at the same
+    /// price per share, and the proceeds are set aside for `claim`.
+    /// Returns the ID of the closed epoch.
+    pub fn close_epoch(env: Env) -> u64 {
+        Self::require_admin(&env);
+        Self::settle_epoch(&env)
     }
 
-    /// Get all pending queued withdrawals
-    pub fn get_pending_withdrawals(env: Env) -> Vec<QueuedWithdrawal> {
-        env.storage().instance()
-            .get(&DataKey::PendingWithdrawals)
-            .unwrap_or(Vec::new(&env))
+    /// Pay out all requests held by `user` in a settled epoch, as far as the
+    /// vault's liquidity allows. Returns the assets received.
+    pub fn claim(env: Env, user: Address, epoch_id: u64) -> Result<i128, Error> {
+        user.require_auth();
+        Self::assert_not_paused(&env);
+        if !Self::get_epoch(env.clone(), epoch_id).settled {
+            return Err(Error::EpochNotSettled);
+        }
+        
+        let mut found = false;
+        let mut claimed = 0;
+        for id in Self::user_withdrawal_ids(&env, &user).iter() {
+            let w = Self::get_withdrawal(env.clone(), id).unwrap();
+            if w.epoch == epoch_id {
+                found = true;
+                claimed += Self::pay_withdrawal(&env, &w, None);
+            }
+        }
+        if !found {
+            return Err(Error::WithdrawalNotFound);
+        }
+        Self::advance_queue_head(&env, Self::queue_head(&env));
+        
+        env.events()
+            .publish((symbol_short!("claim"), user), (epoch_id, claimed));
+        Ok(claimed)
     }
 
-    // ── Rebalance ─────────────────────────────
+    /// ID of the epoch new requests are added to.
+    pub fn current_epoch(env: Env) -> u64 {
+        env.storage()
+            .instance()
+            .get(&DataKey::Queue(QueueKey::CurrentEpoch))
+            .unwrap_or(1)
+    }
+
+    pub fn get_epoch(env: Env, epoch_id: u64) -> RedemptionEpoch {
+        env.storage()
+            .persistent()
+            .get(&DataKey::Queue(QueueKey::Epoch(epoch_id)))
+            .unwrap_or_default()
+    }
+
+    /// Assets set aside for settled but unclaimed requests. They are held
+    /// by the vault but excluded from `total_assets`.
+    pub fn reserved_assets(env: Env) -> i128 {
+        env.storage()
+            .instance()
+            .get(&DataKey::Queue(QueueKey::Reserved))
+            .unwrap_or(0)
+    }
+
+    fn set_epoch(env: &Env, epoch_id: u64, epoch: &RedemptionEpoch) {
+        env.storage()
+            .persistent()
+            .set(&DataKey::Queue(QueueKey::Epoch(epoch_id)), epoch);
+    }
+
+    fn settle_epoch(env: &Env) -> u64 {
+        Self::accrue_management_fee(env);
+        
+        let epoch_id = Self::current_epoch(env.clone());
+        let mut epoch = Self::get_epoch(env.clone(), epoch_id);
+        // Losses realised while raising liquidity are shared by all holders,
+        // so the epoch is priced after the strategies have been drawn on
+        Self::raise_liquidity(env, Self::assets_for_shares(env, epoch.shares, false));
+        epoch.assets = Self::assets_for_shares(env, epoch.shares, false);
+        epoch.settled = true;
+        epoch.settled_at = env.ledger().timestamp();
+        
+        Self::burn_shares(env, &env.current_contract_address(), epoch.shares);
+        let reserved = Self::reserved_assets(env.clone());
+        env.storage()
+            .instance()
+            .set(&DataKey::Queue(QueueKey::Reserved), &reserved.checked_add(epoch.assets).unwrap());
+        
+        Self::set_epoch(env, epoch_id, &epoch);
+        env.storage()
+            .instance()
+            .set(&DataKey::Queue(QueueKey::CurrentEpoch), &(epoch_id + 1));
+        
+        env.events().publish(
+            (symbol_short!("epoch"), symbol_short!("closed")),
+            (epoch_id, epoch.shares, epoch.assets),
+        );
+        epoch_id
+    }
+
+    /// Make sure the vault holds `amount` unreserved assets, pulling the
+    /// shortfall from strategies in withdrawal order.
+    fn raise_liquidity(env: &Env, amount: i128) {
+        let token_client = token::Client::new(env, &Self::get_asset(env));
+        let idle = token_client.balance(&env.current_contract_address()) - Self::reserved_assets(env.clone());
+        Self::pull_from_strategies(env, amount - idle);
+    }
+
+    /// Withdraw up to `amount` from strategies, walking the withdrawal order
+    /// until it is covered. Losses a strategy realises count towards the
+    /// amount covered. Returns `(received, loss)`.
+    fn pull_from_strategies(env: &Env, amount: i128) -> (i128, i128) {
+        if amount <= 0 {
+            return (0, 0);
+        }
+        let mut received = 0;
+        let mut loss = 0;
+        
+        for strategy_addr in Self::get_withdrawal_order(env.clone()).iter() {
+            let shortfall = amount - received - loss;
+            if shortfall <= 0 {
+                break;
+            }
+            if Self::in_emergency_exit(env, &strategy_addr) {
+                continue;
+            }
+            let strategy = StrategyTraitClient::new(env, &strategy_addr);
+            let requested = strategy.max_withdraw().min(shortfall);
+            if requested <= 0 {
+                continue;
+            }
+            let (returned, strategy_loss) = Self::withdraw_from_strategy(env, &strategy_addr, requested);
+            received += returned;
+            loss += strategy_loss;
+        }
+        Self::refresh_nav_snapshot(env);
+        (received, loss)
+    }
+
+    /// Ask a strategy to send `amount` back to the vault and check what
+    /// actually arrived against the vault's own balance. A strategy that hands
+    /// back less than its value dropped has realised a loss, which is written
+    /// off its debt; one that reverts hands back nothing and keeps its debt.
+    /// If the strategy cannot report its value, what arrived is taken as
+    /// what was released. Returns `(received, loss)`.
+    fn withdraw_from_strategy(env: &Env, strategy_addr: &Address, amount: i128) -> (i128, i128) {
+        let token_client = token::Client::new(env, &Self::get_asset(env));
+        let vault = env.current_contract_address();
+        let strategy = StrategyTraitClient::new(env, strategy_addr);
+
+        let value_before = Self::try_strategy_value(env, strategy_addr);
+        let balance_before = token_client.balance(&vault);
+        if strategy.try_withdraw(&amount).is_err() {
+            return (0, 0);
+        }
+        let received = token_client.balance(&vault) - balance_before;
+        let released = match (value_before, Self::try_strategy_value(env, strategy_addr)) {
+            (Some(before), Some(after)) => before - after,
+            _ => received,
+        };
+
+        let loss = (released - received).max(0);
+        Self::adjust_strategy_debt(env, strategy_addr, -(received + loss));
+        if loss > 0 {
+            let mut params = Self::strategy_params(env, strategy_addr);
+            params.total_loss = params.total_loss.checked_add(loss).unwrap();
+            Self::set_params(env, strategy_addr, &params);
+            env.events()
+                .publish((symbol_short!("loss"), strategy_addr.clone()), loss);
+        }
+        (received, loss)
+    }
+
+    /// Pay a request of a settled epoch from the epoch's reserved assets.
+    /// If the vault holds less than is owed (after pulling from strategies
+    /// when enabled) the request is partly filled and the rest stays queued
+    /// with fewer shares; otherwise it is removed from the queue. A keeper
+    /// `bounty` of `(keeper, bps)` is taken out of the payout. Returns the
+    /// assets sent to the user.
+    fn pay_withdrawal(env: &Env, w: &QueuedWithdrawal, bounty: Option<(&Address, u32)>) -> i128 {
+        let mut epoch = Self::get_epoch(env.clone(), w.epoch);
+        // Pro rata share of what is left, so rounding dust goes to the last claimant
+        let mut owed = Self::mul_div(w.shares, epoch.assets, epoch.shares, false);
+        
+        let token: Address = env.storage().instance().get(&DataKey::Token).expect("Token not initialized");
+        let token_client = token::Client::new(env, &token);
+        let vault = env.current_contract_address();
+        let mut available = token_client.balance(&vault);
+        if available < owed && Self::queue_pulls_liquidity(env.clone()) {
+            let (received, loss) = Self::pull_from_strategies(env, owed - available);
+            available += received;
+            // A loss realised to fund this request is borne by it
+            let loss = loss.min(owed);
+            owed -= loss;
+            epoch.assets -= loss;
+            let reserved = Self::reserved_assets(env.clone());
+            env.storage()
+                .instance()
+                .set(&DataKey::Queue(QueueKey::Reserved), &(reserved - loss));
+        }
+        let assets = owed.min(available);
+        // Shares settled by this payment, rounded against the user on partial fills
+        let shares = if assets == owed {
+            w.shares
+        } else {
+            Self::mul_div(w.shares, assets, owed, true).min(w.shares - 1)
+        };
+        if shares <= 0 {
+            // Too little to settle even one share; keep the request whole
+            Self::set_epoch(env, w.epoch, &epoch);
+            return 0;
+        }
+        
+        epoch.shares -= shares;
+        epoch.assets -= assets;
+        Self::set_epoch(env, w.epoch, &epoch);
+        let reserved = Self::reserved_assets(env.clone());
+        env.storage()
+            .instance()
+            .set(&DataKey::Queue(QueueKey::Reserved), &(reserved - assets));
+        
+        // The exit fee follows whoever queued the request, not the claim's holder
+        let fee = Self::fee_on_total(assets, Self::exit_fee_bps(env, &w.user));
+        if shares == w.shares {
+            Self::delete_withdrawal(env, w);
+            env.events().publish(
+                (symbol_short!("WithdrawP"), w.owner.clone()),
+                (w.id, w.shares),
+            );
+        } else {
+            let mut remaining = w.clone();
+            remaining.shares -= shares;
+            env.storage()
+                .persistent()
+                .set(&DataKey::Queue(QueueKey::Request(w.id)), &remaining);
+            env.events().publish(
+                (symbol_short!("partial"), w.owner.clone()),
+                (w.id, assets, remaining.shares),
+            );
+        }
+        
+        let mut paid = assets - fee;
+        if let Some((keeper, bounty_bps)) = bounty {
+            let reward = Self::mul_div(paid, bounty_bps as i128, MAX_BPS as i128, false);
+            if reward > 0 {
+                token_client.transfer(&vault, keeper, &reward);
+                env.events()
+                    .publish((symbol_short!("bounty"), keeper.clone()), (w.id, reward));
+                paid -= reward;
+            }
+        }
+        
+        token_client.transfer(&vault, &w.owner, &paid);
+        Self::pay_asset_fee(env, &vault, fee, symbol_short!("exit"));
+        paid
+    }
+
+    /// Let queue processing and claims withdraw from strategies when the
+    /// vault's idle balance cannot cover a settled request.
+    pub fn set_queue_pulls_liquidity(env: Env, enabled: bool) {
+        Self::require_admin(&env);
+        env.storage()
+            .instance()
+            .set(&DataKey::Queue(QueueKey::PullLiquidity), &enabled);
+        env.events().publish((symbol_short!("QueuePull"),), enabled);
+    }
+
+    pub fn queue_pulls_liquidity(env: Env) -> bool {
+        env.storage()
+            .instance()
+            .get(&DataKey::Queue(QueueKey::PullLiquidity))
+            .unwrap_or(false)
+    }
+
+    /// Cancel the oldest queued withdrawal held by `from`.
+    pub fn cancel_queued_withdrawal(env: Env, from: Address) -> Result<(), Error> {
+        from.require_auth();
+        
+        let id = Self::user_withdrawal_ids(&env, &from)
+            .first()
+            .ok_or(Error::WithdrawalNotFound)?;
+        let w = Self::get_withdrawal(env.clone(), id).unwrap();
+        Self::remove_queued_withdrawal(&env, w)
+    }
+
+    /// Cancel the queued withdrawal `id`. Only the claim's owner may cancel it.
+    pub fn cancel_withdrawal(env: Env, id: u64) -> Result<(), Error> {
+        let w = Self::get_withdrawal(env.clone(), id).ok_or(Error::WithdrawalNotFound)?;
+        w.owner.require_auth();
+        
+        Self::remove_queued_withdrawal(&env, w)
+    }
+
+    /// Drop a request from the queue and release its escrowed shares.
+    /// Requests in a closed epoch have been priced and can only be claimed.
+    fn remove_queued_withdrawal(env: &Env, w: QueuedWithdrawal) -> Result<(), Error> {
+        if w.epoch != Self::current_epoch(env.clone()) {
+            return Err(Error::EpochClosed);
+        }
+        let mut epoch = Self::get_epoch(env.clone(), w.epoch);
+        epoch.shares -= w.shares;
+        Self::set_epoch(env, w.epoch, &epoch);
+        Self::delete_withdrawal(env, &w);
+        if w.id == Self::queue_head(env) {
+            Self::advance_queue_head(env, w.id + 1);
+        }
+        
+        // Release the escrowed shares to the claim's owner
+        Self::move_shares(env, &env.current_contract_address(), &w.owner, w.shares);
+        if w.owner != w.user {
+            Self::record_deposit_time(env, &w.owner, w.shares);
+        }
+        
+        env.events().publish(
+            (symbol_short!("WdrwCncl"),),
+            (w.owner, w.id, w.shares),
+        );
+        Ok(())
+    }
+
+    /// Remove the stored request and its entry in the owner's index.
+    fn delete_withdrawal(env: &Env, w: &QueuedWithdrawal) {
+        env.storage()
+            .persistent()
+            .remove(&DataKey::Queue(QueueKey::Request(w.id)));
+        
+        let user_key = DataKey::Queue(QueueKey::User(w.owner.clone()));
+        let mut user_ids = Self::user_withdrawal_ids(env, &w.owner);
+        if let Some(index) = user_ids.first_index_of(w.id) {
+            user_ids.remove(index);
+        }
+        if user_ids.is_empty() {
+            env.storage().persistent().remove(&user_key);
+        } else {
+            env.storage().persistent().set(&user_key, &user_ids);
+        }
+    }
+
+    pub fn get_withdrawal(env: Env, id: u64) -> Option<QueuedWithdrawal> {
+        env.storage()
+            .persistent()
+            .get(&DataKey::Queue(QueueKey::Request(id)))
+    }
+
+    /// Transfer the pending withdrawal `id` to `to`, who will receive its
+    /// payout or may cancel it.
+    pub fn transfer_withdrawal_claim(env: Env, id: u64, to: Address) -> Result<(), Error> {
+        let mut w = Self::get_withdrawal(env.clone(), id).ok_or(Error::WithdrawalNotFound)?;
+        w.owner.require_auth();
+        
+        Self::delete_withdrawal(&env, &w);
+        let from = w.owner.clone();
+        w.owner = to.clone();
+        env.storage()
+            .persistent()
+            .set(&DataKey::Queue(QueueKey::Request(id)), &w);
+        Self::index_withdrawal(&env, &to, id);
+        
+        env.events()
+            .publish((symbol_short!("ClaimXfer"), from, to), id);
+        Ok(())
+    }
+
+    /// Add `id` to the list of claims held by `owner`, keeping it sorted so
+    /// claims stay in queue order.
+    fn index_withdrawal(env: &Env, owner: &Address, id: u64) {
+        let mut ids = Self::user_withdrawal_ids(env, owner);
+        let index = ids.iter().position(|other| other > id).unwrap_or(ids.len() as usize);
+        ids.insert(index as u32, id);
+        env.storage()
+            .persistent()
+            .set(&DataKey::Queue(QueueKey::User(owner.clone())), &ids);
+    }
+
+    /// All pending withdrawals held by `user`, oldest first.
+    pub fn get_user_withdrawals(env: Env, user: Address) -> Vec<QueuedWithdrawal> {
+        let mut withdrawals = Vec::new(&env);
+        for id in Self::user_withdrawal_ids(&env, &user).iter() {
+            withdrawals.push_back(Self::get_withdrawal(env.clone(), id).unwrap());
+        }
+        withdrawals
+    }
+
+    /// Number of queue slots ahead of `id` (0 = processed next). Slots left
+    /// by cancelled requests between the head and `id` are included, so this is an
+    /// upper bound on the requests still ahead.
+    pub fn get_queue_position(env: Env, id: u64) -> Option<u32> {
+        Self::get_withdrawal(env.clone(), id)?;
+        Some((id - Self::queue_head(&env)) as u32)
+    }
+
+    /// Get the current withdrawal queue threshold
+    pub fn get_withdraw_queue_threshold(env: Env) -> i128 {
+        env.storage().instance()
+            .get(&DataKey::WithdrawQueueThreshold)
+            .unwrap_or(i128::MAX)
+    }
+
+    /// Page through pending withdrawals, oldest first. Reads the `limit`
+    /// queue slots starting `start` slots after the head; empty slots are
+    /// skipped, so a page may hold fewer than `limit` requests.
+    pub fn get_pending_withdrawals(env: Env, start: u32, limit: u32) -> Vec<QueuedWithdrawal> {
+        let tail = Self::queue_tail(&env);
+        let first = Self::queue_head(&env).saturating_add(start as u64).min(tail);
+        let last = first.saturating_add(limit as u64).min(tail);
+        
+        let mut withdrawals = Vec::new(&env);
+        for id in first..last {
+            if let Some(w) = Self::get_withdrawal(env.clone(), id) {
+                withdrawals.push_back(w);
+            }
+        }
+        withdrawals
+    }
+
+    /// Number of queue slots between the head and the tail.
+    pub fn get_queue_length(env: Env) -> u64 {
+        Self::queue_tail(&env) - Self::queue_head(&env)
+    }
+
+    fn queue_head(env: &Env) -> u64 {
+        env.storage()
+            .instance()
+            .get(&DataKey::Queue(QueueKey::Head))
+            .unwrap_or(1)
+    }
+
+    /// Move the head towards the first occupied slot at or after `from`,
+    /// skipping at most `MAX_QUEUE_SKIP` empty slots so a run of cancelled
+    /// requests cannot make one call unaffordable. Returns the new head.
+    fn advance_queue_head(env: &Env, from: u64) -> u64 {
+        let tail = Self::queue_tail(env);
+        let limit = from.saturating_add(MAX_QUEUE_SKIP).min(tail);
+        let mut head = from;
+        while head < limit && Self::get_withdrawal(env.clone(), head).is_none() {
+            head += 1;
+        }
+        Self::set_queue_head(env, head)
+    }
+
+    fn set_queue_head(env: &Env, head: u64) -> u64 {
+        env.storage().instance().set(&DataKey::Queue(QueueKey::Head), &head);
+        head
+    }
+
+    fn queue_tail(env: &Env) -> u64 {
+        env.storage()
+            .instance()
+            .get(&DataKey::Queue(QueueKey::Tail))
+            .unwrap_or(1)
+    }
+
+    fn user_withdrawal_ids(env: &Env, user: &Address) -> Vec<u64> {
+        env.storage()
+            .persistent()
+            .get(&DataKey::Queue(QueueKey::User(user.clone())))
+            .unwrap_or(Vec::new(env))
+    }
+
+    // ── Rebalance ─────────────────────────────
     /// Move funds between strategies according to `allocations`.
     ///
     /// `allocations` maps each strategy address to its *target* balance.
     /// If target > current  → vault sends tokens to the strategy and calls deposit().
-    /// If target < current  → strategy withdraws and sends tokens back to vault.
+    /// If target < current  → strategy withdraws and sends tokens back to vault;
+    ///                        whatever does not arrive is booked as a loss.
     ///
     /// **Access control**: must be called via the multi-sig governance system.
     fn internal_rebalance(env: &Env, max_slippage_bps: u32) -> Result<(), Error> {
@@ -671,7 +1553,7 @@ impl VolatilityShield {
         let oracle = Self::get_oracle(env);
 
         // OR-auth: require that either Admin or Oracle authorised this invocation.
-        Self::require_admin_or_oracle(&env, &admin, &oracle);
+        Self::require_admin_or_oracle(env, &admin, &oracle);
 
         let now = env.ledger().timestamp();
         let last_update = env
@@ -679,11 +1561,11 @@ impl VolatilityShield {
             .instance()
             .get(&DataKey::OracleLastUpdate)
             .unwrap_or(0u64);
-        let max_staleness = Self::max_staleness(&env);
+        let max_staleness = Self::max_staleness(env);
 
-        if now > last_update.checked_add(max_staleness).unwrap_or(u64::MAX) {
+        if now > last_update.saturating_add(max_staleness) {
             env.events().publish(
-                (soroban_sdk::Symbol::new(&env, "StaleOracleRejected"),),
+                (soroban_sdk::Symbol::new(env, "StaleOracleRejected"),),
                 last_update,
             );
             return Err(Error::StaleOracleData);
@@ -695,40 +1577,59 @@ impl VolatilityShield {
             .get(&DataKey::TargetAllocations)
             .ok_or(Error::NotInitialized)?;
 
-        let asset_addr = Self::get_asset(&env);
-        let token_client = token::Client::new(&env, &asset_addr);
+        let asset_addr = Self::get_asset(env);
+        let token_client = token::Client::new(env, &asset_addr);
         let vault = env.current_contract_address();
 
-        // Store initial balances for slippage verification
-        let mut initial_balances: Map<Address, i128> = Map::new(&env);
-        for (strategy_addr, _) in allocations.iter() {
-            let strategy = StrategyClient::new(&env, strategy_addr.clone());
-            initial_balances.set(strategy_addr.clone(), strategy.balance());
-        }
-
-        // Execute rebalance operations
+        // Store initial balances for slippage verification, and cap each
+        // target so no strategy is pushed past its debt ceiling
+        let mut initial_balances: Map<Address, i128> = Map::new(env);
+        let mut targets: Map<Address, i128> = Map::new(env);
         for (strategy_addr, target_allocation) in allocations.iter() {
-            let strategy = StrategyClient::new(&env, strategy_addr.clone());
-            let current_balance = strategy.balance();
+            if Self::in_emergency_exit(env, &strategy_addr) {
+                continue;
+            }
+            let strategy = StrategyTraitClient::new(env, &strategy_addr);
+            let balance = strategy.total_value();
+            let headroom = Self::strategy_debt_limit(env, &strategy_addr)
+                - Self::get_strategy_debt(env.clone(), strategy_addr.clone());
+            initial_balances.set(strategy_addr.clone(), balance);
+            targets.set(
+                strategy_addr.clone(),
+                target_allocation.min(balance.max(0).saturating_add(headroom.max(0))),
+            );
+        }
 
-            if target_allocation > current_balance {
-                // Vault → Strategy
-                let diff = target_allocation - current_balance;
-                token_client.transfer(&vault, &strategy_addr, &diff);
-                strategy.deposit(diff);
-            } else if target_allocation < current_balance {
-                // Strategy → Vault
+        // Execute rebalance operations, withdrawals first so their proceeds
+        // can fund the deposits
+        for (strategy_addr, target_allocation) in targets.iter() {
+            let current_balance = StrategyTraitClient::new(env, &strategy_addr).total_value();
+            if target_allocation < current_balance {
                 let diff = current_balance - target_allocation;
-                strategy.withdraw(diff);
-                token_client.transfer(&strategy_addr, &vault, &diff);
+                // Strategy → Vault (the strategy sends the tokens back)
+                Self::withdraw_from_strategy(env, &strategy_addr, diff);
+            }
+        }
+        for (strategy_addr, target_allocation) in targets.clone().iter() {
+            let strategy = StrategyTraitClient::new(env, &strategy_addr);
+            let current_balance = strategy.total_value();
+            if target_allocation > current_balance {
+                // Vault → Strategy, never out of assets reserved for claims
+                let idle = token_client.balance(&vault) - Self::reserved_assets(env.clone());
+                let diff = (target_allocation - current_balance).min(idle.max(0));
+                if diff > 0 {
+                    token_client.transfer(&vault, &strategy_addr, &diff);
+                    strategy.deposit(&diff);
+                    Self::adjust_strategy_debt(env, &strategy_addr, diff);
+                }
+                targets.set(strategy_addr, current_balance + diff);
             }
-            // If equal, do nothing.
         }
 
         // Verify slippage after all operations
-        for (strategy_addr, target_allocation) in allocations.iter() {
-            let strategy = StrategyClient::new(&env, strategy_addr.clone());
-            let final_balance = strategy.balance();
+        for (strategy_addr, target_allocation) in targets.iter() {
+            let strategy = StrategyTraitClient::new(env, &strategy_addr);
+            let final_balance = strategy.total_value();
             let _initial_balance = initial_balances.get(strategy_addr.clone()).unwrap_or(0);
 
             // Calculate expected balance based on target allocation
@@ -749,7 +1650,7 @@ impl VolatilityShield {
                 if slippage_bps > max_slippage_bps as i128 {
                     // Emit SlippageExceeded event
                     env.events().publish(
-                        (soroban_sdk::Symbol::new(&env, "SlippageExceeded"),),
+                        (soroban_sdk::Symbol::new(env, "SlippageExceeded"),),
                         (
                             strategy_addr.clone(),
                             expected_balance,
@@ -762,6 +1663,8 @@ impl VolatilityShield {
             }
         }
 
+        Self::refresh_nav_snapshot(env);
+
         Ok(())
     }
 
@@ -791,6 +1694,17 @@ impl VolatilityShield {
         // Validate allocations before storing
         Self::validate_allocations(&env, &allocations)?;
 
+        // Exited strategies stay out of the targets until re-enabled
+        let mut allocations = allocations;
+        for strategy in Self::get_strategies(&env).iter() {
+            if Self::in_emergency_exit(&env, &strategy) {
+                env.storage()
+                    .instance()
+                    .remove(&DataKey::ExitedAllocation(strategy.clone()));
+                Self::exclude_allocation(&env, &mut allocations, &strategy);
+            }
+        }
+
         env.storage()
             .instance()
             .set(&DataKey::OracleLastUpdate, &timestamp);
@@ -801,6 +1715,14 @@ impl VolatilityShield {
         Ok(())
     }
 
+    /// Target allocations of the live strategies, as last set by the oracle.
+    pub fn get_target_allocations(env: Env) -> Map<Address, i128> {
+        env.storage()
+            .instance()
+            .get(&DataKey::TargetAllocations)
+            .unwrap_or(Map::new(&env))
+    }
+
     /// Validates allocation data for logical correctness.
     /// - Checks that all allocation percentages sum to 100% (10000 basis points)
     /// - Ensures individual allocations are non-negative
@@ -843,7 +1765,7 @@ impl VolatilityShield {
             .storage()
             .instance()
             .get(&DataKey::Strategies)
-            .unwrap_or(Vec::new(&env));
+            .unwrap_or(Vec::new(env));
         if strategies.contains(strategy.clone()) {
             return Err(Error::AlreadyInitialized);
         }
@@ -851,6 +1773,7 @@ impl VolatilityShield {
         env.storage()
             .instance()
             .set(&DataKey::Strategies, &strategies);
+        Self::set_params(env, &strategy, &Self::strategy_params(env, &strategy));
 
         env.events().publish(
             (symbol_short!("Strategy"), symbol_short!("added")),
@@ -863,32 +1786,127 @@ impl VolatilityShield {
     pub fn harvest(env: Env) -> Result<i128, Error> {
         Self::check_version(&env, 1);
         Self::require_admin(&env);
+        Self::accrue_management_fee(&env);
 
         let strategies = Self::get_strategies(&env);
         if strategies.is_empty() {
             return Err(Error::NoStrategies);
         }
 
-        let mut total_yield: i128 = 0;
+        let mut profit: i128 = 0;
+        let mut loss: i128 = 0;
         for strategy_addr in strategies.iter() {
-            let strategy = StrategyClient::new(&env, strategy_addr);
-            let yield_amount = strategy.balance();
-            total_yield = total_yield.checked_add(yield_amount).unwrap();
-        }
-
-        if total_yield > 0 {
-            let current_assets = Self::total_assets(&env);
-            Self::set_total_assets(
-                env.clone(),
-                current_assets.checked_add(total_yield).unwrap(),
-            );
+            if Self::in_emergency_exit(&env, &strategy_addr) {
+                continue;
+            }
+            let (gain, lost) = Self::report_strategy(&env, &strategy_addr);
+            profit = profit.checked_add(gain).unwrap();
+            loss = loss.checked_add(lost).unwrap();
         }
 
+        let total_yield = Self::settle_report(&env, profit, loss);
         env.events()
             .publish((symbol_short!("harvest"),), total_yield);
         Ok(total_yield)
     }
 
+    /// Harvest a single strategy and book its result. Returns the net yield.
+    pub fn report(env: Env, strategy: Address) -> Result<i128, Error> {
+        Self::check_version(&env, 1);
+        Self::require_admin(&env);
+        if !Self::get_strategies(&env).contains(strategy.clone()) {
+            return Err(Error::NotInitialized);
+        }
+        Self::accrue_management_fee(&env);
+
+        let (profit, loss) = Self::report_strategy(&env, &strategy);
+        let net = Self::settle_report(&env, profit, loss);
+        env.events()
+            .publish((symbol_short!("report"), strategy), (profit, loss));
+        Ok(net)
+    }
+
+    /// Ask a strategy for the profit and loss it realised since its last
+    /// harvest. The net result is folded into the debt so that the same gain
+    /// is never reported twice.
+    fn report_strategy(env: &Env, strategy_addr: &Address) -> (i128, i128) {
+        let strategy = StrategyTraitClient::new(env, strategy_addr);
+        let (profit, loss) = strategy.harvest();
+        Self::adjust_strategy_debt(env, strategy_addr, profit.checked_sub(loss).unwrap());
+
+        let mut params = Self::strategy_params(env, strategy_addr);
+        params.total_gain = params.total_gain.checked_add(profit).unwrap();
+        params.total_loss = params.total_loss.checked_add(loss).unwrap();
+        params.last_report = env.ledger().timestamp();
+        Self::set_params(env, strategy_addr, &params);
+        (profit, loss)
+    }
+
+    /// Charge the performance fee on reported profit and lock what is left
+    /// of it for gradual release. Returns the net yield.
+    fn settle_report(env: &Env, profit: i128, loss: i128) -> i128 {
+        Self::refresh_nav_snapshot(env);
+        let total_yield = profit.checked_sub(loss).unwrap();
+
+        // Performance fees only apply to gains above the high-water mark, so
+        // recovering an earlier drawdown is free for depositors.
+        let mut fee_assets = 0;
+        if total_yield > 0 {
+            let fee_base = Self::gain_above_high_water_mark(env).min(total_yield);
+            fee_assets = fee_base - Self::take_fees(env, fee_base);
+            Self::mint_fee_shares(env, fee_assets, symbol_short!("perf"));
+        }
+        Self::update_high_water_mark(env);
+
+        if total_yield > fee_assets {
+            Self::lock_profit(env, total_yield - fee_assets);
+        }
+        total_yield
+    }
+
+    // ── Profit Unlocking ──────────────────────
+    /// Release reported profit into the share price linearly over `seconds`,
+    /// so a deposit just before a report cannot capture it. While the period
+    /// is non-zero strategies are valued at no more than their debt: losses
+    /// show immediately, gains only once reported. 0 disables locking.
+    pub fn set_profit_unlock_period(env: Env, seconds: u64) {
+        Self::require_admin(&env);
+        // Restart the schedule so already-unlocked profit stays unlocked
+        Self::lock_profit(&env, 0);
+        env.storage().instance().set(&DataKey::ProfitUnlockPeriod, &seconds);
+        env.events().publish((symbol_short!("unlock"),), seconds);
+    }
+
+    pub fn profit_unlock_period(env: Env) -> u64 {
+        env.storage()
+            .instance()
+            .get(&DataKey::ProfitUnlockPeriod)
+            .unwrap_or(0)
+    }
+
+    /// Reported profit not yet counted in `total_assets`.
+    pub fn locked_profit(env: Env) -> i128 {
+        let lock: LockedProfit = env
+            .storage()
+            .instance()
+            .get(&DataKey::LockedProfit)
+            .unwrap_or_default();
+        let period = Self::profit_unlock_period(env.clone());
+        let elapsed = env.ledger().timestamp().saturating_sub(lock.reported_at);
+        if lock.amount <= 0 || elapsed >= period {
+            return 0;
+        }
+        Self::mul_div(lock.amount, (period - elapsed) as i128, period as i128, false)
+    }
+
+    fn lock_profit(env: &Env, amount: i128) {
+        let lock = LockedProfit {
+            amount: Self::locked_profit(env.clone()).checked_add(amount).unwrap(),
+            reported_at: env.ledger().timestamp(),
+        };
+        env.storage().instance().set(&DataKey::LockedProfit, &lock);
+    }
+
     // ── Strategy Health Monitoring ───────────────────
     /// Check health of all strategies and compare expected vs actual balances
     pub fn check_strategy_health(env: Env) -> Result<Vec<Address>, Error> {
@@ -909,8 +1927,13 @@ impl VolatilityShield {
             .unwrap_or(Map::new(&env));
 
         for strategy_addr in strategies.iter() {
-            let strategy = StrategyClient::new(&env, strategy_addr.clone());
-            let actual_balance = strategy.balance();
+            // Exited strategies hold no debt and are not measured
+            if Self::in_emergency_exit(&env, &strategy_addr) {
+                continue;
+            }
+            // A strategy that cannot report its value is unhealthy
+            let actual_value = Self::try_strategy_value(&env, &strategy_addr);
+            let actual_balance = actual_value.unwrap_or(0);
             
             // Get expected balance from allocations
             let expected_balance = expected_allocations
@@ -929,10 +1952,12 @@ impl VolatilityShield {
                 });
             
             // Check if strategy is unhealthy (significant deviation from expected)
-            let balance_deviation = if expected_balance > 0 {
+            let balance_deviation = if actual_value.is_none() {
+                true
+            } else if expected_balance > 0 {
                 // Allow 10% deviation before flagging as unhealthy
                 let deviation_threshold = expected_balance.checked_div(10).unwrap_or(0);
-                (actual_balance as i128 - expected_balance).abs() > deviation_threshold
+                (actual_balance - expected_balance).abs() > deviation_threshold
             } else {
                 // If expected is 0, any positive actual balance is considered healthy
                 false
@@ -960,8 +1985,9 @@ impl VolatilityShield {
         Ok(unhealthy_strategies)
     }
 
-    /// Flag a strategy as unhealthy (admin only)
-    pub fn flag_strategy(env: Env, strategy: Address) -> Result<(), Error> {
+    /// Flag a strategy as unhealthy (admin only). With `emergency_exit` the
+    /// vault also pulls out of the strategy; see `emergency_exit_strategy`.
+    pub fn flag_strategy(env: Env, strategy: Address, emergency_exit: bool) -> Result<(), Error> {
         Self::require_admin(&env);
         
         // Verify strategy exists
@@ -985,50 +2011,183 @@ impl VolatilityShield {
         // Emit StrategyFlagged event
         env.events()
             .publish((symbol_short!("StrategyF"), strategy.clone()), current_time);
+
+        if emergency_exit {
+            Self::emergency_exit_strategy(&env, &strategy);
+        }
         
         Ok(())
     }
 
-    /// Remove a strategy and withdraw all funds first (admin only)
-    pub fn remove_strategy(env: Env, strategy: Address) -> Result<(), Error> {
-        Self::require_admin(&env);
-        
-        // Verify strategy exists
-        let mut strategies = Self::get_strategies(&env);
-        let strategy_index = strategies.iter().position(|s| s == strategy);
-        
-        if strategy_index.is_none() {
-            return Err(Error::NotInitialized);
+    /// Withdraw everything `strategy` can return and write off the rest of
+    /// its debt as a loss. The strategy's target allocation is set aside and
+    /// it stays out of rebalancing, harvesting and the NAV until governance
+    /// re-enables it.
+    fn emergency_exit_strategy(env: &Env, strategy: &Address) {
+        let strategy_client = StrategyTraitClient::new(env, strategy);
+        let available = match strategy_client.try_max_withdraw() {
+            Ok(Ok(available)) => available,
+            _ => 0,
+        };
+        let mut received = 0;
+        if available > 0 {
+            (received, _) = Self::withdraw_from_strategy(env, strategy, available);
         }
-        
-        // Withdraw all funds from strategy first
-        let strategy_client = StrategyClient::new(&env, strategy.clone());
-        let strategy_balance = strategy_client.balance();
-        
-        if strategy_balance > 0 {
-            // Transfer all funds back to vault
-            let asset_addr = Self::get_asset(&env);
-            let token_client = token::Client::new(&env, &asset_addr);
-            
-            // Withdraw from strategy
-            strategy_client.withdraw(strategy_balance);
-            token_client.transfer(&strategy, &env.current_contract_address(), &strategy_balance);
-            
-            // Update total assets to reflect returned funds
-            let current_assets = Self::total_assets(&env);
-            Self::set_total_assets(
-                env.clone(),
-                current_assets.checked_add(strategy_balance).unwrap(),
-            );
+
+        let mut params = Self::strategy_params(env, strategy);
+        let written_off = params.current_debt;
+        params.current_debt = 0;
+        params.total_loss = params.total_loss.checked_add(written_off).unwrap();
+        params.emergency_exit = true;
+        Self::set_params(env, strategy, &params);
+        if written_off > 0 {
+            env.events()
+                .publish((symbol_short!("loss"), strategy.clone()), written_off);
         }
-        
-        // Remove from strategies list
-        strategies.remove(strategy_index.unwrap() as u32);
-        env.storage().instance().set(&DataKey::Strategies, &strategies);
-        
-        // Clean up health data
-        let health_key = DataKey::StrategyHealth(strategy.clone());
+
+        let allocations: Option<Map<Address, i128>> =
+            env.storage().instance().get(&DataKey::TargetAllocations);
+        if let Some(mut allocations) = allocations {
+            Self::exclude_allocation(env, &mut allocations, strategy);
+            env.storage()
+                .instance()
+                .set(&DataKey::TargetAllocations, &allocations);
+        }
+        Self::refresh_nav_snapshot(env);
+
+        env.events()
+            .publish((symbol_short!("exit"), strategy.clone()), (received, written_off));
+    }
+
+    /// Bring an exited strategy back and restore its target allocation. Its
+    /// debt was written off, so whatever it is worth now is booked as
+    /// recovered profit and goes through the performance fee and the profit
+    /// lock like a report would.
+    fn internal_reenable_strategy(env: &Env, strategy: &Address) -> Result<(), Error> {
+        if !Self::get_strategies(env).contains(strategy.clone()) {
+            return Err(Error::NotInitialized);
+        }
+        Self::accrue_management_fee(env);
+
+        // Reset the strategy's own baseline so the recovery is not reported twice
+        let strategy_client = StrategyTraitClient::new(env, strategy);
+        strategy_client.harvest();
+        let recovered = strategy_client.total_value().max(0);
+
+        let mut params = Self::strategy_params(env, strategy);
+        params.emergency_exit = false;
+        params.current_debt = recovered;
+        params.total_gain = params.total_gain.checked_add(recovered).unwrap();
+        params.last_report = env.ledger().timestamp();
+        Self::set_params(env, strategy, &params);
+        Self::settle_report(env, recovered, 0);
+
+        let key = DataKey::ExitedAllocation(strategy.clone());
+        if let Some(allocation) = env.storage().instance().get::<_, i128>(&key) {
+            let mut allocations: Map<Address, i128> = env
+                .storage()
+                .instance()
+                .get(&DataKey::TargetAllocations)
+                .unwrap_or(Map::new(env));
+            allocations.set(strategy.clone(), allocation);
+            env.storage()
+                .instance()
+                .set(&DataKey::TargetAllocations, &allocations);
+            env.storage().instance().remove(&key);
+        }
+
+        env.events()
+            .publish((symbol_short!("reenable"), strategy.clone()), recovered);
+        Ok(())
+    }
+
+    /// Move the target allocation of an exited `strategy` out of
+    /// `allocations`, keeping it aside for when the strategy is re-enabled.
+    fn exclude_allocation(env: &Env, allocations: &mut Map<Address, i128>, strategy: &Address) {
+        if let Some(allocation) = allocations.get(strategy.clone()) {
+            env.storage()
+                .instance()
+                .set(&DataKey::ExitedAllocation(strategy.clone()), &allocation);
+            allocations.remove(strategy.clone());
+        }
+    }
+
+    /// Value reported by `strategy`, or `None` if the call reverts.
+    fn try_strategy_value(env: &Env, strategy: &Address) -> Option<i128> {
+        match StrategyTraitClient::new(env, strategy).try_total_value() {
+            Ok(Ok(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn in_emergency_exit(env: &Env, strategy: &Address) -> bool {
+        Self::strategy_params(env, strategy).emergency_exit
+    }
+
+    /// Set the order in which strategies are drawn on to raise liquidity.
+    /// Only the listed strategies are used; every entry must be registered.
+    pub fn set_withdrawal_order(env: Env, order: Vec<Address>) -> Result<(), Error> {
+        Self::require_admin(&env);
+        let strategies = Self::get_strategies(&env);
+        for strategy in order.iter() {
+            if !strategies.contains(strategy) {
+                return Err(Error::NotInitialized);
+            }
+        }
+        env.storage().instance().set(&DataKey::WithdrawalOrder, &order);
+        env.events().publish((symbol_short!("WdrwOrder"),), order);
+        Ok(())
+    }
+
+    /// Strategy withdrawal order; defaults to registration order.
+    pub fn get_withdrawal_order(env: Env) -> Vec<Address> {
+        env.storage()
+            .instance()
+            .get(&DataKey::WithdrawalOrder)
+            .unwrap_or_else(|| Self::get_strategies(&env))
+    }
+
+    /// Remove a strategy and withdraw all funds first (admin only)
+    pub fn remove_strategy(env: Env, strategy: Address) -> Result<(), Error> {
+        Self::require_admin(&env);
+        
+        // Verify strategy exists
+        let mut strategies = Self::get_strategies(&env);
+        let strategy_index = strategies.iter().position(|s| s == strategy);
+        
+        if strategy_index.is_none() {
+            return Err(Error::NotInitialized);
+        }
+        
+        // Withdraw all funds from strategy first; whatever it fails to
+        // send back is written off as a loss
+        let strategy_client = StrategyTraitClient::new(&env, &strategy);
+        let strategy_value = Self::try_strategy_value(&env, &strategy)
+            .or_else(|| strategy_client.try_max_withdraw().ok().and_then(|value| value.ok()))
+            .unwrap_or(0);
+        let mut strategy_balance = 0;
+        
+        if strategy_value > 0 {
+            (strategy_balance, _) = Self::withdraw_from_strategy(&env, &strategy, strategy_value);
+        }
+        
+        // Remove from strategies list
+        strategies.remove(strategy_index.unwrap() as u32);
+        env.storage().instance().set(&DataKey::Strategies, &strategies);
+        let order: Option<Vec<Address>> = env.storage().instance().get(&DataKey::WithdrawalOrder);
+        if let Some(mut order) = order {
+            if let Some(index) = order.first_index_of(strategy.clone()) {
+                order.remove(index);
+                env.storage().instance().set(&DataKey::WithdrawalOrder, &order);
+            }
+        }
+        
+        // Clean up health and params data
+        let health_key = DataKey::StrategyHealth(strategy.clone());
         env.storage().instance().remove(&health_key);
+        env.storage().instance().remove(&DataKey::StrategyParams(strategy.clone()));
+        env.storage().instance().remove(&DataKey::ExitedAllocation(strategy.clone()));
+        Self::refresh_nav_snapshot(&env);
         
         // Emit StrategyRemoved event
         env.events()
@@ -1044,6 +2203,456 @@ impl VolatilityShield {
             .get(&DataKey::StrategyHealth(strategy))
     }
 
+    // ── Share Token (SEP-41) ──────────────────
+    /// Vault shares are exposed as a SEP-41 token over the same
+    /// `DataKey::Balance` entries that deposit and withdraw maintain.
+    pub fn balance(env: Env, id: Address) -> i128 {
+        env.storage()
+            .persistent()
+            .get(&DataKey::Balance(id))
+            .unwrap_or(0)
+    }
+
+    pub fn total_supply(env: Env) -> i128 {
+        Self::total_shares(&env)
+    }
+
+    pub fn allowance(env: Env, from: Address, spender: Address) -> i128 {
+        Self::read_allowance(&env, &from, &spender).amount
+    }
+
+    pub fn approve(env: Env, from: Address, spender: Address, amount: i128, expiration_ledger: u32) {
+        from.require_auth();
+        if amount < 0 {
+            panic!("negative amount");
+        }
+        if amount > 0 && expiration_ledger < env.ledger().sequence() {
+            panic!("expiration_ledger is less than ledger seq when amount > 0");
+        }
+
+        let key = DataKey::Allowance(AllowanceDataKey {
+            from: from.clone(),
+            spender: spender.clone(),
+        });
+        env.storage().temporary().set(
+            &key,
+            &AllowanceValue {
+                amount,
+                expiration_ledger,
+            },
+        );
+        if amount > 0 {
+            let live_for = expiration_ledger
+                .checked_sub(env.ledger().sequence())
+                .unwrap();
+            env.storage().temporary().extend_ttl(&key, live_for, live_for);
+        }
+
+        env.events().publish(
+            (symbol_short!("approve"), from, spender),
+            (amount, expiration_ledger),
+        );
+    }
+
+    pub fn transfer(env: Env, from: Address, to: Address, amount: i128) {
+        from.require_auth();
+        Self::move_shares(&env, &from, &to, amount);
+        Self::record_deposit_time(&env, &to, amount);
+    }
+
+    pub fn transfer_from(env: Env, spender: Address, from: Address, to: Address, amount: i128) {
+        spender.require_auth();
+        Self::spend_allowance(&env, &from, &spender, amount);
+        Self::move_shares(&env, &from, &to, amount);
+        Self::record_deposit_time(&env, &to, amount);
+    }
+
+    /// Shares carry the same precision as the underlying asset.
+    pub fn decimals(env: Env) -> u32 {
+        token::Client::new(&env, &Self::get_asset(&env)).decimals()
+    }
+
+    pub fn name(env: Env) -> String {
+        Self::share_metadata(&env).name
+    }
+
+    pub fn symbol(env: Env) -> String {
+        Self::share_metadata(&env).symbol
+    }
+
+    pub fn set_share_metadata(env: Env, name: String, symbol: String) {
+        Self::require_admin(&env);
+        env.storage()
+            .instance()
+            .set(&DataKey::ShareMetadata, &ShareMetadata { name, symbol });
+    }
+
+    fn share_metadata(env: &Env) -> ShareMetadata {
+        env.storage()
+            .instance()
+            .get(&DataKey::ShareMetadata)
+            .unwrap_or(ShareMetadata {
+                name: String::from_str(env, "XHedge Volatility Shield Share"),
+                symbol: String::from_str(env, "xVS"),
+            })
+    }
+
+    fn read_allowance(env: &Env, from: &Address, spender: &Address) -> AllowanceValue {
+        let key = DataKey::Allowance(AllowanceDataKey {
+            from: from.clone(),
+            spender: spender.clone(),
+        });
+        match env.storage().temporary().get::<_, AllowanceValue>(&key) {
+            Some(allowance) if allowance.expiration_ledger >= env.ledger().sequence() => allowance,
+            Some(allowance) => AllowanceValue {
+                amount: 0,
+                expiration_ledger: allowance.expiration_ledger,
+            },
+            None => AllowanceValue {
+                amount: 0,
+                expiration_ledger: 0,
+            },
+        }
+    }
+
+    fn spend_allowance(env: &Env, from: &Address, spender: &Address, amount: i128) {
+        let allowance = Self::read_allowance(env, from, spender);
+        if allowance.amount < amount {
+            panic!("insufficient allowance");
+        }
+        if amount > 0 {
+            let key = DataKey::Allowance(AllowanceDataKey {
+                from: from.clone(),
+                spender: spender.clone(),
+            });
+            env.storage().temporary().set(
+                &key,
+                &AllowanceValue {
+                    amount: allowance.amount - amount,
+                    expiration_ledger: allowance.expiration_ledger,
+                },
+            );
+        }
+    }
+
+    fn move_shares(env: &Env, from: &Address, to: &Address, amount: i128) {
+        if amount < 0 {
+            panic!("negative amount");
+        }
+        let from_balance = Self::balance(env.clone(), from.clone());
+        if from_balance < amount {
+            panic!("insufficient shares for transfer");
+        }
+        Self::write_balance(env, from, from_balance - amount);
+        let to_balance = Self::balance(env.clone(), to.clone());
+        Self::write_balance(env, to, to_balance.checked_add(amount).unwrap());
+
+        env.events().publish(
+            (symbol_short!("transfer"), from.clone(), to.clone()),
+            amount,
+        );
+    }
+
+    /// Credit newly issued shares to `to` and grow the total supply.
+    fn mint_shares(env: &Env, to: &Address, shares: i128) {
+        let balance = Self::balance(env.clone(), to.clone());
+        Self::write_balance(env, to, balance.checked_add(shares).unwrap());
+        let total_shares = Self::total_shares(env);
+        Self::write_total_shares(env, total_shares.checked_add(shares).unwrap());
+        env.events()
+            .publish((symbol_short!("mint"), to.clone()), shares);
+    }
+
+    /// Destroy shares held by `from` and shrink the total supply.
+    fn burn_shares(env: &Env, from: &Address, shares: i128) {
+        let balance = Self::balance(env.clone(), from.clone());
+        if balance < shares {
+            panic!("insufficient shares for withdrawal");
+        }
+        Self::write_balance(env, from, balance - shares);
+        let total_shares = Self::total_shares(env);
+        Self::write_total_shares(env, total_shares.checked_sub(shares).unwrap());
+        env.events()
+            .publish((symbol_short!("burn"), from.clone()), shares);
+    }
+
+    // ── NAV ───────────────────────────────────
+    /// Assets held by registered strategies. Served from the NAV snapshot
+    /// while it is younger than `NavMaxAge`, otherwise read live.
+    fn strategy_assets(env: &Env) -> i128 {
+        let max_age = Self::get_nav_max_age(env.clone());
+        if max_age > 0 {
+            if let Some(snapshot) = Self::get_nav_snapshot(env.clone()) {
+                if env.ledger().timestamp().saturating_sub(snapshot.timestamp) <= max_age {
+                    return snapshot.strategy_assets;
+                }
+            }
+        }
+        Self::live_strategy_assets(env)
+    }
+
+    fn live_strategy_assets(env: &Env) -> i128 {
+        let cap_at_debt = Self::profit_unlock_period(env.clone()) > 0;
+        let mut total: i128 = 0;
+        for strategy_addr in Self::get_strategies(env).iter() {
+            if Self::in_emergency_exit(env, &strategy_addr) {
+                continue;
+            }
+            let strategy = StrategyTraitClient::new(env, &strategy_addr);
+            let mut value = strategy.total_value();
+            if cap_at_debt {
+                value = value.min(Self::get_strategy_debt(env.clone(), strategy_addr));
+            }
+            total = total.checked_add(value).unwrap();
+        }
+        total
+    }
+
+    /// Re-read every strategy and store the result as the NAV snapshot.
+    fn refresh_nav_snapshot(env: &Env) -> NavSnapshot {
+        let snapshot = NavSnapshot {
+            strategy_assets: Self::live_strategy_assets(env),
+            timestamp: env.ledger().timestamp(),
+        };
+        env.storage().instance().set(&DataKey::NavSnapshot, &snapshot);
+        snapshot
+    }
+
+    /// Refresh the cached strategy valuation (admin only).
+    pub fn update_nav(env: Env) -> i128 {
+        Self::require_admin(&env);
+        let snapshot = Self::refresh_nav_snapshot(&env);
+        env.events()
+            .publish((symbol_short!("nav"),), snapshot.strategy_assets);
+        Self::total_assets(&env)
+    }
+
+    /// Maximum age in seconds of a usable NAV snapshot; 0 disables caching.
+    pub fn set_nav_max_age(env: Env, seconds: u64) {
+        Self::require_admin(&env);
+        env.storage().instance().set(&DataKey::NavMaxAge, &seconds);
+    }
+
+    pub fn get_nav_max_age(env: Env) -> u64 {
+        env.storage()
+            .instance()
+            .get(&DataKey::NavMaxAge)
+            .unwrap_or(0)
+    }
+
+    pub fn get_nav_snapshot(env: Env) -> Option<NavSnapshot> {
+        env.storage().instance().get(&DataKey::NavSnapshot)
+    }
+
+    /// Principal the vault has recorded for `strategy`, including realised yield.
+    pub fn get_strategy_debt(env: Env, strategy: Address) -> i128 {
+        Self::strategy_params(&env, &strategy).current_debt
+    }
+
+    fn adjust_strategy_debt(env: &Env, strategy: &Address, delta: i128) {
+        let mut params = Self::strategy_params(env, strategy);
+        params.current_debt = params.current_debt.checked_add(delta).unwrap().max(0);
+        Self::set_params(env, strategy, &params);
+    }
+
+    /// Debt limits and results for a registered strategy.
+    pub fn get_strategy_params(env: Env, strategy: Address) -> Option<StrategyParams> {
+        env.storage()
+            .instance()
+            .get(&DataKey::StrategyParams(strategy))
+    }
+
+    /// Cap what `strategy` may hold at `debt_ratio` bps of total assets and
+    /// at most `max_debt`. New strategies start without a ceiling.
+    pub fn set_strategy_params(
+        env: Env,
+        strategy: Address,
+        debt_ratio: u32,
+        max_debt: i128,
+    ) -> Result<(), Error> {
+        Self::require_admin(&env);
+        if !Self::get_strategies(&env).contains(strategy.clone()) {
+            return Err(Error::NotInitialized);
+        }
+        if debt_ratio > MAX_BPS {
+            return Err(Error::InvalidDebtRatio);
+        }
+        if max_debt < 0 {
+            return Err(Error::NegativeAmount);
+        }
+        let mut params = Self::strategy_params(&env, &strategy);
+        params.debt_ratio = debt_ratio;
+        params.max_debt = max_debt;
+        Self::set_params(&env, &strategy, &params);
+        env.events().publish(
+            (symbol_short!("debtlimit"), strategy),
+            (debt_ratio, max_debt),
+        );
+        Ok(())
+    }
+
+    fn strategy_params(env: &Env, strategy: &Address) -> StrategyParams {
+        env.storage()
+            .instance()
+            .get(&DataKey::StrategyParams(strategy.clone()))
+            .unwrap_or(StrategyParams {
+                debt_ratio: MAX_BPS,
+                max_debt: i128::MAX,
+                current_debt: 0,
+                total_gain: 0,
+                total_loss: 0,
+                last_report: 0,
+                emergency_exit: false,
+            })
+    }
+
+    fn set_params(env: &Env, strategy: &Address, params: &StrategyParams) {
+        env.storage()
+            .instance()
+            .set(&DataKey::StrategyParams(strategy.clone()), params);
+    }
+
+    /// Most debt `strategy` may carry at the vault's current size.
+    fn strategy_debt_limit(env: &Env, strategy: &Address) -> i128 {
+        let params = Self::strategy_params(env, strategy);
+        if params.debt_ratio >= MAX_BPS {
+            return params.max_debt;
+        }
+        Self::mul_div(Self::total_assets(env), params.debt_ratio as i128, MAX_BPS as i128, false)
+            .min(params.max_debt)
+    }
+
+    // ── Previews & Limits ─────────────────────
+    // Previews quote a caller that pays the entry and exit fees in full;
+    // exempt accounts and holders past the holding period get more.
+
+    /// Shares `deposit(assets)` would mint right now (rounded down).
+    pub fn preview_deposit(env: Env, assets: i128) -> i128 {
+        let fees = Self::get_entry_exit_fees(env.clone());
+        let fee = Self::fee_on_total(assets, fees.deposit_bps);
+        Self::shares_for_assets(&env, assets - fee, false)
+    }
+
+    /// Assets, including the entry fee, needed to mint exactly `shares`
+    /// shares (rounded up).
+    pub fn preview_mint(env: Env, shares: i128) -> i128 {
+        let fees = Self::get_entry_exit_fees(env.clone());
+        let assets = Self::assets_for_shares(&env, shares, true);
+        assets + Self::fee_on_raw(assets, fees.deposit_bps)
+    }
+
+    /// Shares that must be burned to receive exactly `assets` after the
+    /// exit fee and, above the queue threshold, the express fee (rounded up).
+    pub fn preview_withdraw(env: Env, assets: i128) -> i128 {
+        let fees = Self::get_entry_exit_fees(env.clone());
+        let fee = Self::fee_on_raw(assets, fees.withdraw_bps);
+        let gross = Self::gross_up_express_fee(&env, assets + fee);
+        Self::shares_for_assets(&env, gross, true)
+    }
+
+    /// Assets paid out, net of the exit fee and, above the queue threshold,
+    /// the express fee, for redeeming `shares` shares (rounded down).
+    pub fn preview_redeem(env: Env, shares: i128) -> i128 {
+        let fees = Self::get_entry_exit_fees(env.clone());
+        let assets = Self::assets_for_shares(&env, shares, false);
+        let assets = assets - Self::express_fee_on(&env, assets);
+        assets - Self::fee_on_total(assets, fees.withdraw_bps)
+    }
+
+    /// Largest asset amount `user` can deposit without hitting a cap.
+    /// Returns `i128::MAX` when no cap applies and 0 while paused.
+    pub fn max_deposit(env: Env, user: Address) -> i128 {
+        if Self::is_paused(env.clone()) {
+            return 0;
+        }
+        let per_user = match Self::share_headroom(&env, &user) {
+            i128::MAX => i128::MAX,
+            headroom => Self::assets_for_shares(&env, headroom, false),
+        };
+        match per_user.min(Self::asset_headroom(&env)) {
+            i128::MAX => i128::MAX,
+            net => net + Self::fee_on_raw(net, Self::entry_fee_bps(&env, &user)),
+        }
+    }
+
+    /// Largest share amount `user` can receive from a deposit.
+    pub fn max_mint(env: Env, user: Address) -> i128 {
+        if Self::is_paused(env.clone()) {
+            return 0;
+        }
+        let global = match Self::asset_headroom(&env) {
+            i128::MAX => i128::MAX,
+            headroom => Self::shares_for_assets(&env, headroom, false),
+        };
+        Self::share_headroom(&env, &user).min(global)
+    }
+
+    /// Largest asset amount `user` can withdraw immediately, i.e. without
+    /// exceeding the per-tx cap or, unless the express lane is open, being
+    /// routed to the withdrawal queue.
+    pub fn max_withdraw(env: Env, user: Address) -> i128 {
+        if Self::is_paused(env.clone()) {
+            return 0;
+        }
+        let shares = Self::balance(env.clone(), user.clone());
+        let assets = Self::assets_for_shares(&env, shares, false).min(Self::instant_withdraw_limit(&env));
+        let assets = assets - Self::express_fee_on(&env, assets);
+        assets - Self::fee_on_total(assets, Self::exit_fee_bps(&env, &user))
+    }
+
+    /// Largest share amount `user` can redeem immediately.
+    pub fn max_redeem(env: Env, user: Address) -> i128 {
+        if Self::is_paused(env.clone()) {
+            return 0;
+        }
+        let shares = Self::balance(env.clone(), user);
+        let limit = Self::instant_withdraw_limit(&env);
+        if Self::assets_for_shares(&env, shares, false) <= limit {
+            return shares;
+        }
+        Self::shares_for_assets(&env, limit, false).min(shares)
+    }
+
+    /// Shares `user` may still accumulate under `MaxDepositPerUser`.
+    fn share_headroom(env: &Env, user: &Address) -> i128 {
+        let max_per_user: i128 = env
+            .storage()
+            .instance()
+            .get(&DataKey::MaxDepositPerUser)
+            .unwrap_or(i128::MAX);
+        if max_per_user == i128::MAX {
+            return i128::MAX;
+        }
+        let balance = Self::balance(env.clone(), user.clone());
+        max_per_user.saturating_sub(balance).max(0)
+    }
+
+    /// Assets the vault may still accept under `MaxTotalAssets`.
+    fn asset_headroom(env: &Env) -> i128 {
+        let max_total: i128 = env
+            .storage()
+            .instance()
+            .get(&DataKey::MaxTotalAssets)
+            .unwrap_or(i128::MAX);
+        if max_total == i128::MAX {
+            return i128::MAX;
+        }
+        max_total.saturating_sub(Self::total_assets(env)).max(0)
+    }
+
+    /// Largest asset amount a single withdrawal can pay out without queuing.
+    fn instant_withdraw_limit(env: &Env) -> i128 {
+        let max_per_tx: i128 = env
+            .storage()
+            .instance()
+            .get(&DataKey::MaxWithdrawPerTx)
+            .unwrap_or(i128::MAX);
+        if Self::get_express_exit_fee(env.clone()) > 0 {
+            return max_per_tx;
+        }
+        max_per_tx.min(Self::get_withdraw_queue_threshold(env.clone()))
+    }
+
     // ── View helpers ──────────────────────────
     pub fn has_admin(env: &Env) -> bool {
         env.storage().instance().has(&DataKey::Admin)
@@ -1056,12 +2665,22 @@ impl VolatilityShield {
             .expect("Not initialized")
     }
 
-    /// Total assets managed by the vault: vault token balance + sum of strategy balances.
+    /// Total assets managed by the vault: vault token balance + sum of strategy balances,
+    /// less reserved assets and profit that is still locked.
     pub fn total_assets(env: &Env) -> i128 {
-        env.storage()
+        let token: Address = env
+            .storage()
             .instance()
-            .get(&DataKey::TotalAssets)
-            .unwrap_or(0)
+            .get(&DataKey::Token)
+            .expect("Token not initialized");
+        let idle = token::Client::new(env, &token)
+            .balance(&env.current_contract_address())
+            .checked_sub(Self::reserved_assets(env.clone()))
+            .unwrap();
+        idle.checked_add(Self::strategy_assets(env))
+            .unwrap()
+            .checked_sub(Self::locked_profit(env.clone()))
+            .unwrap()
     }
 
     pub fn total_shares(env: &Env) -> i128 {
@@ -1106,13 +2725,6 @@ impl VolatilityShield {
             .unwrap_or(0)
     }
 
-    pub fn balance(env: Env, user: Address) -> i128 {
-        env.storage()
-            .persistent()
-            .get(&DataKey::Balance(user))
-            .unwrap_or(0)
-    }
-
     pub fn get_guardians(env: Env) -> Vec<Address> {
         env.storage()
             .instance()
@@ -1126,7 +2738,7 @@ impl VolatilityShield {
 
     // ── Internal Helpers ──────────────────────
     pub fn take_fees(env: &Env, amount: i128) -> i128 {
-        let fee_pct = Self::fee_percentage(&env);
+        let fee_pct = Self::fee_percentage(env);
         if fee_pct == 0 {
             return amount;
         }
@@ -1139,53 +2751,55 @@ impl VolatilityShield {
     }
 
     pub fn convert_to_shares(env: Env, amount: i128) -> i128 {
+        Self::shares_for_assets(&env, amount, false)
+    }
+
+    pub fn convert_to_assets(env: Env, shares: i128) -> i128 {
+        Self::assets_for_shares(&env, shares, false)
+    }
+
+    /// Share amount worth `amount` assets, rounded down or up.
+    ///
+    /// Both sides of the ratio carry a virtual offset, so an empty vault
+    /// prices shares 1:1 and a donation to a near-empty vault is mostly
+    /// captured by the virtual shares instead of the first real depositor.
+    fn shares_for_assets(env: &Env, amount: i128, round_up: bool) -> i128 {
         if amount < 0 {
             panic!("negative amount");
         }
-        let total_shares = Self::total_shares(&env);
-        let total_assets = Self::total_assets(&env);
-        if total_shares == 0 || total_assets == 0 {
-            return amount;
-        }
-        amount
-            .checked_mul(total_shares)
-            .unwrap()
-            .checked_div(total_assets)
-            .unwrap()
+        let total_shares = Self::effective_total_shares(env).checked_add(VIRTUAL_SHARES).unwrap();
+        let total_assets = Self::total_assets(env).checked_add(VIRTUAL_ASSETS).unwrap();
+        Self::mul_div(amount, total_shares, total_assets, round_up)
     }
 
-    pub fn convert_to_assets(env: Env, shares: i128) -> i128 {
+    /// Asset amount worth `shares` shares, rounded down or up.
+    fn assets_for_shares(env: &Env, shares: i128, round_up: bool) -> i128 {
         if shares < 0 {
             panic!("negative amount");
         }
-        let total_shares = Self::total_shares(&env);
-        let total_assets = Self::total_assets(&env);
-        if total_shares == 0 {
-            return shares;
-        }
-        shares
-            .checked_mul(total_assets)
-            .unwrap()
-            .checked_div(total_shares)
-            .unwrap()
+        let total_shares = Self::effective_total_shares(env).checked_add(VIRTUAL_SHARES).unwrap();
+        let total_assets = Self::total_assets(env).checked_add(VIRTUAL_ASSETS).unwrap();
+        Self::mul_div(shares, total_assets, total_shares, round_up)
     }
 
-    pub fn set_total_assets(env: Env, amount: i128) {
-        env.storage().instance().set(&DataKey::TotalAssets, &amount);
+    fn mul_div(a: i128, b: i128, denominator: i128, round_up: bool) -> i128 {
+        let product = a.checked_mul(b).unwrap();
+        let quotient = product.checked_div(denominator).unwrap();
+        if round_up && product % denominator != 0 {
+            quotient + 1
+        } else {
+            quotient
+        }
     }
 
-    pub fn set_total_shares(env: Env, amount: i128) {
+    fn write_total_shares(env: &Env, amount: i128) {
         env.storage().instance().set(&DataKey::TotalShares, &amount);
     }
 
-    pub fn set_balance(env: Env, user: Address, amount: i128) {
+    fn write_balance(env: &Env, user: &Address, amount: i128) {
         env.storage()
             .persistent()
-            .set(&DataKey::Balance(user), &amount);
-    }
-
-    pub fn set_token(env: Env, token: Address) {
-        env.storage().instance().set(&DataKey::Token, &token);
+            .set(&DataKey::Balance(user.clone()), &amount);
     }
 
     fn require_admin(env: &Env) -> Address {
@@ -1194,6 +2808,308 @@ impl VolatilityShield {
         admin
     }
 
+    // ── Fees ──────────────────────────────────
+    /// Set the performance fee charged on harvested yield, in basis points.
+    pub fn set_fee_percentage(env: Env, fee_percentage: u32) -> Result<(), Error> {
+        Self::require_admin(&env);
+        if fee_percentage > MAX_BPS {
+            return Err(Error::InvalidFeeRate);
+        }
+        env.storage()
+            .instance()
+            .set(&DataKey::FeePercentage, &fee_percentage);
+        env.events().publish((symbol_short!("FeePct"),), fee_percentage);
+        Ok(())
+    }
+
+    pub fn set_treasury(env: Env, treasury: Address) {
+        Self::require_admin(&env);
+        env.storage().instance().set(&DataKey::Treasury, &treasury);
+        env.events().publish((symbol_short!("Treasury"),), treasury);
+    }
+
+    /// Current price of one share in assets, scaled by `PRICE_PRECISION`.
+    pub fn price_per_share(env: Env) -> i128 {
+        let total_shares = Self::effective_total_shares(&env).checked_add(VIRTUAL_SHARES).unwrap();
+        let total_assets = Self::total_assets(&env).checked_add(VIRTUAL_ASSETS).unwrap();
+        Self::mul_div(total_assets, PRICE_PRECISION, total_shares, false)
+    }
+
+    /// Highest price per share on which performance fees have been settled,
+    /// scaled by `PRICE_PRECISION`.
+    pub fn high_water_mark(env: Env) -> i128 {
+        env.storage()
+            .instance()
+            .get(&DataKey::HighWaterMark)
+            .unwrap_or(PRICE_PRECISION)
+    }
+
+    /// Assets held above what the outstanding shares were worth at the
+    /// high-water mark. Locked profit counts here: it has been reported and
+    /// charged already, and only its release into the share price is delayed.
+    fn gain_above_high_water_mark(env: &Env) -> i128 {
+        let total_shares = Self::total_shares(env).checked_add(VIRTUAL_SHARES).unwrap();
+        let total_assets = Self::assets_with_locked_profit(env).checked_add(VIRTUAL_ASSETS).unwrap();
+        let value_at_mark = Self::mul_div(
+            Self::high_water_mark(env.clone()),
+            total_shares,
+            PRICE_PRECISION,
+            true,
+        );
+        total_assets.saturating_sub(value_at_mark).max(0)
+    }
+
+    /// Raise the mark to the current price per share, counting locked
+    /// profit like `gain_above_high_water_mark` does.
+    fn update_high_water_mark(env: &Env) {
+        let total_shares = Self::effective_total_shares(env).checked_add(VIRTUAL_SHARES).unwrap();
+        let total_assets = Self::assets_with_locked_profit(env).checked_add(VIRTUAL_ASSETS).unwrap();
+        let price = Self::mul_div(total_assets, PRICE_PRECISION, total_shares, false);
+        if price > Self::high_water_mark(env.clone()) {
+            env.storage().instance().set(&DataKey::HighWaterMark, &price);
+            env.events().publish((symbol_short!("hwm"),), price);
+        }
+    }
+
+    fn assets_with_locked_profit(env: &Env) -> i128 {
+        Self::total_assets(env)
+            .checked_add(Self::locked_profit(env.clone()))
+            .unwrap()
+    }
+
+    /// Set the annual management fee in basis points. Fees accrued at the
+    /// previous rate are settled first.
+    pub fn set_management_fee(env: Env, fee_bps: u32) -> Result<(), Error> {
+        Self::require_admin(&env);
+        if fee_bps > MAX_MANAGEMENT_FEE_BPS {
+            return Err(Error::InvalidFeeRate);
+        }
+        Self::accrue_management_fee(&env);
+        env.storage().instance().set(&DataKey::ManagementFee, &fee_bps);
+        env.events().publish((symbol_short!("MgmtFee"),), fee_bps);
+        Ok(())
+    }
+
+    pub fn management_fee(env: Env) -> u32 {
+        env.storage()
+            .instance()
+            .get(&DataKey::ManagementFee)
+            .unwrap_or(0)
+    }
+
+    /// Management fee, in assets, accrued since it was last realised.
+    pub fn pending_management_fee(env: Env) -> i128 {
+        let fee_bps = Self::management_fee(env.clone());
+        let last_accrual: u64 = env
+            .storage()
+            .instance()
+            .get(&DataKey::LastFeeAccrual)
+            .unwrap_or(env.ledger().timestamp());
+        let elapsed = env.ledger().timestamp().saturating_sub(last_accrual);
+        if fee_bps == 0 || elapsed == 0 || Self::total_shares(&env) == 0 {
+            return 0;
+        }
+        Self::total_assets(&env)
+            .checked_mul(fee_bps as i128)
+            .unwrap()
+            .checked_mul(elapsed as i128)
+            .unwrap()
+            .checked_div(MAX_BPS as i128 * SECONDS_PER_YEAR as i128)
+            .unwrap()
+    }
+
+    /// Realise the streamed management fee by minting shares to the treasury.
+    fn accrue_management_fee(env: &Env) {
+        let fee_assets = Self::pending_management_fee(env.clone());
+        env.storage()
+            .instance()
+            .set(&DataKey::LastFeeAccrual, &env.ledger().timestamp());
+        Self::mint_fee_shares(env, fee_assets, symbol_short!("mgmt"));
+    }
+
+    /// Total supply once the pending management fee has been minted.
+    fn effective_total_shares(env: &Env) -> i128 {
+        let pending = Self::pending_management_fee(env.clone());
+        Self::total_shares(env)
+            .checked_add(Self::fee_shares_for(env, pending))
+            .unwrap()
+    }
+
+    /// Total value, in assets, of all fees charged to date.
+    pub fn accrued_fees(env: Env) -> i128 {
+        env.storage()
+            .instance()
+            .get(&DataKey::AccruedFees)
+            .unwrap_or(0)
+    }
+
+    /// Shares worth `fee_assets` once minted. The fee is already part of
+    /// `total_assets`, so the shares are priced against the assets that
+    /// remain for existing holders.
+    fn fee_shares_for(env: &Env, fee_assets: i128) -> i128 {
+        if fee_assets <= 0 {
+            return 0;
+        }
+        let total_shares = Self::total_shares(env).checked_add(VIRTUAL_SHARES).unwrap();
+        let remaining_assets = Self::total_assets(env)
+            .checked_add(VIRTUAL_ASSETS)
+            .unwrap()
+            .checked_sub(fee_assets)
+            .unwrap();
+        if remaining_assets <= 0 {
+            return 0;
+        }
+        Self::mul_div(fee_assets, total_shares, remaining_assets, false)
+    }
+
+    /// Pay `fee_assets` to the treasury by minting shares worth that amount.
+    fn mint_fee_shares(env: &Env, fee_assets: i128, kind: soroban_sdk::Symbol) -> i128 {
+        let fee_shares = Self::fee_shares_for(env, fee_assets);
+        if fee_shares == 0 {
+            return 0;
+        }
+
+        Self::mint_shares(env, &Self::treasury(env), fee_shares);
+        let accrued = Self::accrued_fees(env.clone());
+        env.storage()
+            .instance()
+            .set(&DataKey::AccruedFees, &accrued.checked_add(fee_assets).unwrap());
+
+        env.events()
+            .publish((symbol_short!("fee"), kind), (fee_assets, fee_shares));
+        fee_shares
+    }
+
+    /// Configure the deposit and withdrawal fees. Each rate must be below
+    /// 100%.
+    pub fn set_entry_exit_fees(
+        env: Env,
+        deposit_bps: u32,
+        withdraw_bps: u32,
+        min_holding_period: u64,
+    ) -> Result<(), Error> {
+        Self::require_admin(&env);
+        if deposit_bps >= MAX_BPS || withdraw_bps >= MAX_BPS {
+            return Err(Error::InvalidFeeRate);
+        }
+        let fees = EntryExitFees {
+            deposit_bps,
+            withdraw_bps,
+            min_holding_period,
+        };
+        env.storage().instance().set(&DataKey::EntryExitFees, &fees);
+        env.events().publish(
+            (symbol_short!("EntryExit"),),
+            (deposit_bps, withdraw_bps, min_holding_period),
+        );
+        Ok(())
+    }
+
+    pub fn get_entry_exit_fees(env: Env) -> EntryExitFees {
+        env.storage()
+            .instance()
+            .get(&DataKey::EntryExitFees)
+            .unwrap_or_default()
+    }
+
+    /// Exempt `account` from (or subject it again to) entry and exit fees.
+    pub fn set_fee_exempt(env: Env, account: Address, exempt: bool) {
+        Self::require_admin(&env);
+        let key = DataKey::FeeExempt(account.clone());
+        if exempt {
+            env.storage().persistent().set(&key, &true);
+        } else {
+            env.storage().persistent().remove(&key);
+        }
+        env.events()
+            .publish((symbol_short!("FeeExempt"), account), exempt);
+    }
+
+    pub fn is_fee_exempt(env: Env, account: Address) -> bool {
+        env.storage()
+            .persistent()
+            .get(&DataKey::FeeExempt(account))
+            .unwrap_or(false)
+    }
+
+    fn entry_fee_bps(env: &Env, from: &Address) -> u32 {
+        if Self::is_fee_exempt(env.clone(), from.clone()) {
+            return 0;
+        }
+        Self::get_entry_exit_fees(env.clone()).deposit_bps
+    }
+
+    /// Withdrawal fee owed by `owner`: zero when exempt or once its shares
+    /// have been held for the minimum holding period.
+    fn exit_fee_bps(env: &Env, owner: &Address) -> u32 {
+        if Self::is_fee_exempt(env.clone(), owner.clone()) {
+            return 0;
+        }
+        let fees = Self::get_entry_exit_fees(env.clone());
+        if fees.min_holding_period > 0 {
+            let last_deposit: Option<u64> = env
+                .storage()
+                .persistent()
+                .get(&DataKey::LastDeposit(owner.clone()));
+            if let Some(at) = last_deposit {
+                if env.ledger().timestamp().saturating_sub(at) >= fees.min_holding_period {
+                    return 0;
+                }
+            }
+        }
+        fees.withdraw_bps
+    }
+
+    /// Move the holding clock of `account` after it received `shares`. The
+    /// clock becomes the balance-weighted average of the old clock and now
+    /// (rounded towards now), so a dust transfer cannot restart someone
+    /// else's holding period while fresh shares still count in full.
+    fn record_deposit_time(env: &Env, account: &Address, shares: i128) {
+        let now = env.ledger().timestamp();
+        let key = DataKey::LastDeposit(account.clone());
+        let held = Self::balance(env.clone(), account.clone()).checked_sub(shares).unwrap();
+        let since = match env.storage().persistent().get::<_, u64>(&key) {
+            Some(since) if held > 0 && shares > 0 => {
+                let age = now.saturating_sub(since) as i128;
+                let total = held.checked_add(shares).unwrap();
+                now - Self::mul_div(age, held, total, false) as u64
+            }
+            Some(since) if shares <= 0 => since,
+            _ => now,
+        };
+        env.storage().persistent().set(&key, &since);
+    }
+
+    /// Fee included in a gross `amount` (rounded up).
+    fn fee_on_total(amount: i128, fee_bps: u32) -> i128 {
+        Self::mul_div(amount, fee_bps as i128, MAX_BPS as i128, true)
+    }
+
+    /// Fee to add on top of a net `amount` (rounded up).
+    fn fee_on_raw(amount: i128, fee_bps: u32) -> i128 {
+        Self::mul_div(amount, fee_bps as i128, (MAX_BPS - fee_bps) as i128, true)
+    }
+
+    /// Transfer a fee paid in the underlying asset from `from` to the treasury.
+    fn pay_asset_fee(env: &Env, from: &Address, fee: i128, kind: soroban_sdk::Symbol) {
+        if fee <= 0 {
+            return;
+        }
+        let token: Address = env
+            .storage()
+            .instance()
+            .get(&DataKey::Token)
+            .expect("Token not initialized");
+        token::Client::new(env, &token).transfer(from, &Self::treasury(env), &fee);
+        let accrued = Self::accrued_fees(env.clone());
+        env.storage()
+            .instance()
+            .set(&DataKey::AccruedFees, &accrued.checked_add(fee).unwrap());
+
+        env.events()
+            .publish((symbol_short!("fee"), kind), (fee, 0i128));
+    }
+
     // ── Emergency Pause ──────────────────────────
     pub fn set_paused(env: Env, state: bool) {
         Self::require_admin(&env);
@@ -1330,4 +3246,22 @@ impl VolatilityShield {
     }
 }
 
+// Test-only setters that seed share state directly. They bypass the share
+// accounting, so they are never part of the deployed contract.
+#[cfg(test)]
+#[contractimpl]
+impl VolatilityShield {
+    /// Overwrite the share supply (admin only).
+    pub fn set_total_shares(env: Env, amount: i128) {
+        Self::require_admin(&env);
+        Self::write_total_shares(&env, amount);
+    }
+
+    /// Overwrite an account's share balance (admin only).
+    pub fn set_balance(env: Env, user: Address, amount: i128) {
+        Self::require_admin(&env);
+        Self::write_balance(&env, &user, amount);
+    }
+}
+
 mod test;
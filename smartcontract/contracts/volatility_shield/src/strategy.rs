@@ -1,13 +1,29 @@
-use soroban_sdk::{contractclient, Env};
+use soroban_sdk::{contractclient, Address, Env};
 
 #[contractclient(name = "StrategyTraitClient")]
 pub trait StrategyTrait {
     /// Deposit assets into the strategy
     fn deposit(env: Env, amount: i128);
 
-    /// Withdraw assets from the strategy
+    /// Withdraw assets from the strategy and transfer them back to the vault.
+    /// The vault counts only what arrives; anything short is booked as a loss.
     fn withdraw(env: Env, amount: i128);
 
     /// Get the current balance of the strategy
     fn balance(env: Env) -> i128;
+
+    /// Value of everything the strategy manages, including unrealised yield
+    fn total_value(env: Env) -> i128;
+
+    /// Realise yield since the last harvest and report it as `(profit, loss)`
+    fn harvest(env: Env) -> (i128, i128);
+
+    /// Largest amount that can be withdrawn from the strategy right now
+    fn max_withdraw(env: Env) -> i128;
+
+    /// Underlying asset the strategy accepts
+    fn asset(env: Env) -> Address;
+
+    /// Vault the strategy reports to
+    fn vault(env: Env) -> Address;
 }
@@ -1,9 +1,8 @@
 #![cfg(test)]
 use super::*;
-use soroban_sdk::testutils::Events as EventsTrait;
 use soroban_sdk::token::Client as TokenClient;
 use soroban_sdk::token::StellarAssetClient;
-use soroban_sdk::{testutils::Address as _, testutils::Ledger as _, Address, Env, IntoVal, Map};
+use soroban_sdk::{testutils::Address as _, testutils::Ledger as _, Address, Env, Map};
 
 extern crate std;
 
@@ -17,14 +16,25 @@ fn create_token_contract<'a>(
     (contract_id.address(), stellar_asset_client, token_client)
 }
 
+/// Mint or burn underlying tokens so the vault's idle balance equals `amount`.
+fn set_vault_assets(env: &Env, token_id: &Address, vault: &Address, amount: i128) {
+    let token_client = TokenClient::new(env, token_id);
+    let current = token_client.balance(vault);
+    if amount > current {
+        StellarAssetClient::new(env, token_id).mint(vault, &(amount - current));
+    } else if amount < current {
+        token_client.burn(vault, &(current - amount));
+    }
+}
+
 #[test]
 fn test_init_stores_roles() {
     let env = Env::default();
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
-    let asset = Address::generate(&env);
+    let (asset, _, _) = create_token_contract(&env, &admin);
     let oracle = Address::generate(&env);
     let treasury = Address::generate(&env);
 
@@ -46,7 +56,7 @@ fn test_init_stores_roles() {
 #[test]
 fn test_init_already_initialized() {
     let env = Env::default();
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
@@ -64,10 +74,14 @@ fn test_init_already_initialized() {
 #[test]
 fn test_convert_to_assets() {
     let env = Env::default();
-    let contract_id = env.register_contract(None, VolatilityShield);
+    env.mock_all_auths();
+
+    let token_admin = Address::generate(&env);
+    let (asset, _, _) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
     let admin = Address::generate(&env);
-    let asset = Address::generate(&env);
     let oracle = Address::generate(&env);
     let treasury = Address::generate(&env);
     let guardians = soroban_sdk::vec![&env, admin.clone()];
@@ -77,26 +91,26 @@ fn test_convert_to_assets() {
     assert_eq!(client.convert_to_assets(&100), 100);
 
     // 2. Test exact conversion
-    client.set_total_assets(&100);
+    set_vault_assets(&env, &asset, &contract_id, 100);
     client.set_total_shares(&100);
     assert_eq!(client.convert_to_assets(&50), 50);
 
-    // 3. Test rounding down (favors vault)
-    client.set_total_assets(&10);
-    client.set_total_shares(&4);
-    assert_eq!(client.convert_to_assets(&3), 7);
+    // 3. Test rounding down (favors vault): 3 * (2000 + 1000) / (1000 + 1000) = 4.5
+    set_vault_assets(&env, &asset, &contract_id, 2000);
+    client.set_total_shares(&1000);
+    assert_eq!(client.convert_to_assets(&3), 4);
 
-    // 4. Test larger values
-    client.set_total_assets(&1000);
-    client.set_total_shares(&300);
-    assert_eq!(client.convert_to_assets(&100), 333);
+    // 4. Test larger values: 100 * (10000 + 1000) / (2000 + 1000) = 366.67
+    set_vault_assets(&env, &asset, &contract_id, 10000);
+    client.set_total_shares(&2000);
+    assert_eq!(client.convert_to_assets(&100), 366);
 }
 
 #[test]
 #[should_panic(expected = "negative amount")]
 fn test_convert_to_assets_negative() {
     let env = Env::default();
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
     client.convert_to_assets(&-1);
 }
@@ -104,10 +118,14 @@ fn test_convert_to_assets_negative() {
 #[test]
 fn test_convert_to_shares() {
     let env = Env::default();
-    let contract_id = env.register_contract(None, VolatilityShield);
+    env.mock_all_auths();
+
+    let token_admin = Address::generate(&env);
+    let (asset, _, _) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
     let admin = Address::generate(&env);
-    let asset = Address::generate(&env);
     let oracle = Address::generate(&env);
     let treasury = Address::generate(&env);
     let guardians = soroban_sdk::vec![&env, admin.clone()];
@@ -116,20 +134,18 @@ fn test_convert_to_shares() {
     // 1. Initial Deposit (total_shares = 0)
     assert_eq!(client.convert_to_shares(&100), 100);
 
-    // 2. Precision Loss (favors vault by rounding down)
-    client.set_total_assets(&3);
-    client.set_total_shares(&1);
-    assert_eq!(client.convert_to_shares(&10), 3);
+    // 2. Precision Loss (favors vault by rounding down): 11 * (1000 + 1000) / (3000 + 1000) = 5.5
+    set_vault_assets(&env, &asset, &contract_id, 3000);
+    client.set_total_shares(&1000);
+    assert_eq!(client.convert_to_shares(&11), 5);
 
     // 3. Standard Proportional Minting
-    client.set_total_assets(&1000);
-    client.set_total_shares(&500);
     assert_eq!(client.convert_to_shares(&200), 100);
 
-    // 4. Rounding Down with Large Values
-    client.set_total_assets(&300);
+    // 4. Rounding Down with Large Values: 100 * (1000 + 1000) / (300 + 1000) = 153.85
+    set_vault_assets(&env, &asset, &contract_id, 300);
     client.set_total_shares(&1000);
-    assert_eq!(client.convert_to_shares(&100), 333);
+    assert_eq!(client.convert_to_shares(&100), 153);
 }
 
 #[test]
@@ -137,7 +153,7 @@ fn test_strategy_registry() {
     let env = Env::default();
     env.mock_all_auths();
 
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
@@ -166,7 +182,7 @@ fn test_strategy_registry() {
 #[should_panic(expected = "negative amount")]
 fn test_convert_to_shares_negative() {
     let env = Env::default();
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
     client.convert_to_shares(&-1);
 }
@@ -174,7 +190,7 @@ fn test_convert_to_shares_negative() {
 #[test]
 fn test_take_fees() {
     let env = Env::default();
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
     let admin = Address::generate(&env);
     let asset = Address::generate(&env);
@@ -197,7 +213,7 @@ fn test_deposit_success() {
     let token_admin = Address::generate(&env);
     let (token_id, stellar_asset_client, _) = create_token_contract(&env, &token_admin);
 
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
@@ -225,7 +241,7 @@ fn test_withdraw_success() {
     let token_admin = Address::generate(&env);
     let (token_id, stellar_asset_client, token_client) = create_token_contract(&env, &token_admin);
 
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
@@ -234,18 +250,17 @@ fn test_withdraw_success() {
 
     let guardians = soroban_sdk::vec![&env, admin.clone()]; client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
     client.set_total_shares(&1000);
-    client.set_total_assets(&5000);
 
     let user = Address::generate(&env);
     client.set_balance(&user, &100);
 
-    stellar_asset_client.mint(&contract_id, &5000);
+    stellar_asset_client.mint(&contract_id, &9000);
 
     client.withdraw(&user, &50);
 
     assert_eq!(client.balance(&user), 50);
     assert_eq!(client.total_shares(), 950);
-    assert_eq!(client.total_assets(), 4750);
+    assert_eq!(client.total_assets(), 8750);
     assert_eq!(token_client.balance(&user), 250);
 }
 
@@ -254,7 +269,7 @@ fn test_rebalance_admin_auth_accepted() {
     let env = Env::default();
     env.mock_all_auths();
 
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
@@ -276,7 +291,7 @@ fn test_rebalance_oracle_auth_accepted() {
     let env = Env::default();
     env.mock_all_auths();
 
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
@@ -299,7 +314,7 @@ fn test_multisig_set_paused() {
     let env = Env::default();
     env.mock_all_auths();
 
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
@@ -325,7 +340,7 @@ fn test_multisig_add_strategy() {
     let env = Env::default();
     env.mock_all_auths();
 
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
@@ -348,7 +363,7 @@ fn test_multisig_unauthorized_propose() {
     let env = Env::default();
     env.mock_all_auths();
 
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
@@ -365,7 +380,7 @@ fn test_guardian_crud() {
     let env = Env::default();
     env.mock_all_auths();
 
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
@@ -396,8 +411,8 @@ mod strategy_health_tests {
     use super::*;
     use mock_strategy::MockStrategyClient;
 
-    fn create_mock_strategy(env: &Env) -> (Address, MockStrategyClient) {
-        let mock_strategy_id = env.register_contract(None, mock_strategy::MockStrategy);
+    fn create_mock_strategy(env: &Env) -> (Address, MockStrategyClient<'_>) {
+        let mock_strategy_id = env.register(mock_strategy::MockStrategy, ());
         let mock_client = MockStrategyClient::new(env, &mock_strategy_id);
         (mock_strategy_id, mock_client)
     }
@@ -408,7 +423,7 @@ mod strategy_health_tests {
         let env = Env::default();
         env.mock_all_auths();
 
-        let contract_id = env.register_contract(None, VolatilityShield);
+        let contract_id = env.register(VolatilityShield, ());
         let client = VolatilityShieldClient::new(&env, &contract_id);
 
         let admin = Address::generate(&env);
@@ -441,7 +456,7 @@ mod strategy_health_tests {
         let env = Env::default();
         env.mock_all_auths();
 
-        let contract_id = env.register_contract(None, VolatilityShield);
+        let contract_id = env.register(VolatilityShield, ());
         let client = VolatilityShieldClient::new(&env, &contract_id);
 
         let admin = Address::generate(&env);
@@ -474,7 +489,7 @@ mod strategy_health_tests {
         let env = Env::default();
         env.mock_all_auths();
 
-        let contract_id = env.register_contract(None, VolatilityShield);
+        let contract_id = env.register(VolatilityShield, ());
         let client = VolatilityShieldClient::new(&env, &contract_id);
 
         let admin = Address::generate(&env);
@@ -488,7 +503,7 @@ mod strategy_health_tests {
         client.propose_action(&admin, &ActionType::AddStrategy(mock_strategy_id.clone()));
 
         // Flag strategy as unhealthy
-        client.flag_strategy(&mock_strategy_id);
+        client.flag_strategy(&mock_strategy_id, &false);
 
         // Check health data reflects flagged status
         let health = client.get_strategy_health(&mock_strategy_id);
@@ -503,7 +518,7 @@ mod strategy_health_tests {
         let env = Env::default();
         env.mock_all_auths();
 
-        let contract_id = env.register_contract(None, VolatilityShield);
+        let contract_id = env.register(VolatilityShield, ());
         let client = VolatilityShieldClient::new(&env, &contract_id);
 
         let admin = Address::generate(&env);
@@ -514,11 +529,10 @@ mod strategy_health_tests {
         client.init(&admin, &asset, &oracle, &treasury, &0u32, &guardians, &1u32);
 
         let nonexistent_strategy = Address::generate(&env);
-        client.flag_strategy(&nonexistent_strategy);
+        client.flag_strategy(&nonexistent_strategy, &false);
     }
 
     #[test]
-    #[ignore] // TODO: contract design mismatch with upstream; needs investigation
     fn test_remove_strategy_with_funds() {
         let env = Env::default();
         env.mock_all_auths_allowing_non_root_auth();
@@ -526,11 +540,11 @@ mod strategy_health_tests {
         let token_admin = Address::generate(&env);
         let (token_id, stellar_asset_client, token_client) = create_token_contract(&env, &token_admin);
 
-        let contract_id = env.register_contract(None, VolatilityShield);
+        let contract_id = env.register(VolatilityShield, ());
         let client = VolatilityShieldClient::new(&env, &contract_id);
 
         let admin = Address::generate(&env);
-        let asset = Address::generate(&env);
+        let _asset = Address::generate(&env);
         let oracle = Address::generate(&env);
         let treasury = Address::generate(&env);
         let guardians = soroban_sdk::vec![&env, admin.clone()];
@@ -539,8 +553,9 @@ mod strategy_health_tests {
         let (mock_strategy_id, mock_client) = create_mock_strategy(&env);
         client.propose_action(&admin, &ActionType::AddStrategy(mock_strategy_id.clone()));
 
-        // Mint tokens to vault and deposit to strategy
-        stellar_asset_client.mint(&contract_id, &1000);
+        // The strategy holds 1000 of the vault's tokens
+        mock_client.initialize(&contract_id, &token_id);
+        stellar_asset_client.mint(&mock_strategy_id, &1000);
         mock_client.deposit(&1000);
 
         // Remove strategy
@@ -564,7 +579,7 @@ mod strategy_health_tests {
         let env = Env::default();
         env.mock_all_auths();
 
-        let contract_id = env.register_contract(None, VolatilityShield);
+        let contract_id = env.register(VolatilityShield, ());
         let client = VolatilityShieldClient::new(&env, &contract_id);
 
         let admin = Address::generate(&env);
@@ -592,7 +607,7 @@ mod strategy_health_tests {
         let env = Env::default();
         env.mock_all_auths();
 
-        let contract_id = env.register_contract(None, VolatilityShield);
+        let contract_id = env.register(VolatilityShield, ());
         let client = VolatilityShieldClient::new(&env, &contract_id);
 
         let admin = Address::generate(&env);
@@ -612,7 +627,7 @@ mod strategy_health_tests {
         let env = Env::default();
         env.mock_all_auths();
 
-        let contract_id = env.register_contract(None, VolatilityShield);
+        let contract_id = env.register(VolatilityShield, ());
         let client = VolatilityShieldClient::new(&env, &contract_id);
 
         let admin = Address::generate(&env);
@@ -631,7 +646,7 @@ mod strategy_health_tests {
         assert!(health.unwrap().is_healthy);
 
         // After flagging, should be unhealthy
-        client.flag_strategy(&mock_strategy_id);
+        client.flag_strategy(&mock_strategy_id, &false);
         let health = client.get_strategy_health(&mock_strategy_id);
         assert!(health.is_some());
         assert!(!health.unwrap().is_healthy);
@@ -644,7 +659,7 @@ mod strategy_health_tests {
         let env = Env::default();
         env.mock_all_auths();
 
-        let contract_id = env.register_contract(None, VolatilityShield);
+        let contract_id = env.register(VolatilityShield, ());
         let client = VolatilityShieldClient::new(&env, &contract_id);
 
         let admin = Address::generate(&env);
@@ -666,7 +681,7 @@ fn test_timelock_duration_setting() {
     let env = Env::default();
     env.mock_all_auths();
 
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
@@ -687,7 +702,7 @@ fn test_timelock_prevents_premature_execution() {
     let env = Env::default();
     env.mock_all_auths();
 
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
@@ -706,7 +721,7 @@ fn test_timelock_prevents_premature_execution() {
     // Propose action - should succeed but not execute because timelock hasn't elapsed
     // With threshold 1, it tries to execute immediately but timelock blocks it
     // The proposal is created but not executed
-    let proposal_id = client.propose_action(&admin, &ActionType::SetPaused(true));
+    let _proposal_id = client.propose_action(&admin, &ActionType::SetPaused(true));
     assert!(!client.is_paused()); // Should not be paused because timelock blocked execution
 }
 
@@ -715,7 +730,7 @@ fn test_timelock_blocks_immediate_execution() {
     let env = Env::default();
     env.mock_all_auths();
 
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
@@ -742,7 +757,7 @@ fn test_timelock_with_multisig_approval() {
     let env = Env::default();
     env.mock_all_auths();
 
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
@@ -778,7 +793,7 @@ fn test_timelock_zero_duration_allows_immediate_execution() {
     let env = Env::default();
     env.mock_all_auths();
 
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
@@ -801,7 +816,7 @@ fn test_timelock_events_emitted() {
     let env = Env::default();
     env.mock_all_auths();
 
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
@@ -836,7 +851,7 @@ fn test_withdraw_queue_threshold_setting() {
     let env = Env::default();
     env.mock_all_auths();
 
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
@@ -858,7 +873,7 @@ fn test_withdraw_below_threshold_processes_immediately() {
     let token_admin = Address::generate(&env);
     let (token_id, stellar_asset_client, token_client) = create_token_contract(&env, &token_admin);
 
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
@@ -873,9 +888,8 @@ fn test_withdraw_below_threshold_processes_immediately() {
     // Setup user with balance
     let user = Address::generate(&env);
     client.set_total_shares(&1000);
-    client.set_total_assets(&5000);
     client.set_balance(&user, &200);
-    stellar_asset_client.mint(&contract_id, &5000);
+    stellar_asset_client.mint(&contract_id, &9000);
 
     // Withdraw 50 shares (converts to 250 assets, below threshold)
     client.withdraw(&user, &50);
@@ -883,7 +897,7 @@ fn test_withdraw_below_threshold_processes_immediately() {
     // Should process immediately
     assert_eq!(client.balance(&user), 150);
     assert_eq!(token_client.balance(&user), 250);
-    assert_eq!(client.get_pending_withdrawals().len(), 0);
+    assert_eq!(client.get_pending_withdrawals(&0, &100).len(), 0);
 }
 
 #[test]
@@ -894,7 +908,7 @@ fn test_withdraw_above_threshold_queues() {
     let token_admin = Address::generate(&env);
     let (token_id, stellar_asset_client, _) = create_token_contract(&env, &token_admin);
 
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
@@ -909,19 +923,21 @@ fn test_withdraw_above_threshold_queues() {
     // Setup user with balance
     let user = Address::generate(&env);
     client.set_total_shares(&1000);
-    client.set_total_assets(&5000);
     client.set_balance(&user, &500);
-    stellar_asset_client.mint(&contract_id, &5000);
+    stellar_asset_client.mint(&contract_id, &9000);
 
     // Queue 300 shares via queue_withdraw (converts to 1500 assets, above threshold)
     client.queue_withdraw(&user, &300);
 
-    // Should be queued; balance is NOT reduced until processed
-    assert_eq!(client.balance(&user), 500);
-    let pending = client.get_pending_withdrawals();
+    // Should be queued with the shares held in escrow
+    assert_eq!(client.balance(&user), 200);
+    assert_eq!(client.balance(&contract_id), 300);
+    assert_eq!(client.total_shares(), 1000);
+    let pending = client.get_pending_withdrawals(&0, &100);
     assert_eq!(pending.len(), 1);
     assert_eq!(pending.get(0).unwrap().user, user);
     assert_eq!(pending.get(0).unwrap().shares, 300);
+    assert_eq!(pending.get(0).unwrap().assets, 1500);
 }
 
 #[test]
@@ -932,7 +948,7 @@ fn test_process_withdraw_queue() {
     let token_admin = Address::generate(&env);
     let (token_id, stellar_asset_client, token_client) = create_token_contract(&env, &token_admin);
 
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
@@ -947,22 +963,23 @@ fn test_process_withdraw_queue() {
     // Setup user with balance
     let user = Address::generate(&env);
     client.set_total_shares(&1000);
-    client.set_total_assets(&5000);
     client.set_balance(&user, &500);
-    stellar_asset_client.mint(&contract_id, &5000);
+    stellar_asset_client.mint(&contract_id, &9000);
 
     // Queue a withdrawal directly (300 shares = 1500 assets > threshold of 1000)
     client.queue_withdraw(&user, &300);
-    assert_eq!(client.get_pending_withdrawals().len(), 1);
+    assert_eq!(client.get_pending_withdrawals(&0, &100).len(), 1);
 
     // Process the queue
     client.process_queued_withdrawals(&1);
 
     // Withdrawal should be processed
-    assert_eq!(client.get_pending_withdrawals().len(), 0);
+    assert_eq!(client.get_pending_withdrawals(&0, &100).len(), 0);
     assert_eq!(token_client.balance(&user), 1500); // 300 shares * 5 = 1500 assets
+    assert_eq!(client.balance(&user), 200);
+    assert_eq!(client.balance(&contract_id), 0);
     assert_eq!(client.total_shares(), 700);
-    assert_eq!(client.total_assets(), 3500);
+    assert_eq!(client.total_assets(), 7500);
 }
 
 #[test]
@@ -973,7 +990,7 @@ fn test_cancel_withdraw() {
     let token_admin = Address::generate(&env);
     let (token_id, stellar_asset_client, _) = create_token_contract(&env, &token_admin);
 
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
@@ -988,36 +1005,32 @@ fn test_cancel_withdraw() {
     // Setup user with balance
     let user = Address::generate(&env);
     client.set_total_shares(&1000);
-    client.set_total_assets(&5000);
     client.set_balance(&user, &500);
-    stellar_asset_client.mint(&contract_id, &5000);
+    stellar_asset_client.mint(&contract_id, &9000);
 
     // Queue a withdrawal directly (300 shares = 1500 assets > threshold of 1000)
     client.queue_withdraw(&user, &300);
-    // Balance stays at 500 until the withdrawal is processed
-    assert_eq!(client.balance(&user), 500);
-    assert_eq!(client.get_pending_withdrawals().len(), 1);
+    assert_eq!(client.balance(&user), 200);
+    assert_eq!(client.get_pending_withdrawals(&0, &100).len(), 1);
 
-    // Cancel the withdrawal
+    // Cancelling releases the escrowed shares without minting new ones
     client.cancel_queued_withdrawal(&user);
 
-    // cancel_queued_withdrawal returns shares to balance even though queue_withdraw didn't reduce it
-    // So balance becomes 500 + 300 = 800 (contract behavior)
-    assert_eq!(client.balance(&user), 800);
-    assert_eq!(client.get_pending_withdrawals().len(), 0);
+    assert_eq!(client.balance(&user), 500);
+    assert_eq!(client.balance(&contract_id), 0);
+    assert_eq!(client.total_shares(), 1000);
+    assert_eq!(client.get_pending_withdrawals(&0, &100).len(), 0);
 }
 
 #[test]
-#[ignore] // TODO: contract design mismatch with upstream; needs investigation
-#[should_panic(expected = "user already has a pending withdrawal")]
-fn test_cannot_queue_multiple_withdrawals() {
+fn test_multiple_withdrawals_per_user_with_ids() {
     let env = Env::default();
     env.mock_all_auths_allowing_non_root_auth();
 
     let token_admin = Address::generate(&env);
-    let (token_id, stellar_asset_client, _) = create_token_contract(&env, &token_admin);
+    let (token_id, stellar_asset_client, token_client) = create_token_contract(&env, &token_admin);
 
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
@@ -1029,19 +1042,141 @@ fn test_cannot_queue_multiple_withdrawals() {
     client.set_withdraw_queue_threshold(&1000);
 
     let user = Address::generate(&env);
+    let other = Address::generate(&env);
     client.set_total_shares(&1000);
-    client.set_total_assets(&5000);
-    // Give user enough balance for both withdrawals
     client.set_balance(&user, &600);
-    stellar_asset_client.mint(&contract_id, &5000);
+    client.set_balance(&other, &300);
+    stellar_asset_client.mint(&contract_id, &9000);
 
-    // Queue first withdrawal via queue_withdraw (300 shares = 1500 assets, above threshold of 1000)
-    client.queue_withdraw(&user, &300);
-    // User now has 300 shares remaining
+    let first = client.queue_withdraw(&user, &300);
+    let second = client.queue_withdraw(&other, &300);
+    let third = client.queue_withdraw(&user, &250);
+    assert_eq!((first, second, third), (1, 2, 3));
+    assert_eq!(client.balance(&user), 50);
+
+    let request = client.get_withdrawal(&third).unwrap();
+    assert_eq!(request.user, user);
+    assert_eq!(request.shares, 250);
+    assert_eq!(client.get_withdrawal(&99), None);
+
+    let mine = client.get_user_withdrawals(&user);
+    assert_eq!(mine.len(), 2);
+    assert_eq!(mine.get(0).unwrap().id, first);
+    assert_eq!(mine.get(1).unwrap().id, third);
+
+    assert_eq!(client.get_queue_position(&first), Some(0));
+    assert_eq!(client.get_queue_position(&third), Some(2));
+
+    // Cancelling by ID targets exactly that request
+    client.cancel_withdrawal(&third);
+    assert_eq!(client.balance(&user), 300);
+    assert_eq!(client.get_withdrawal(&third), None);
+    assert_eq!(client.try_cancel_withdrawal(&third), Err(Ok(Error::WithdrawalNotFound)));
+
+    client.process_queued_withdrawals(&1);
+    assert_eq!(token_client.balance(&user), 1500);
+    assert_eq!(client.get_queue_position(&first), None);
+    assert_eq!(client.get_queue_position(&second), Some(0));
+}
+
+#[test]
+fn test_withdrawal_queue_pagination_and_holes() {
+    let env = Env::default();
+    env.mock_all_auths_allowing_non_root_auth();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, token_client) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    client.set_withdraw_queue_threshold(&100);
+
+    let user = Address::generate(&env);
+    client.set_total_shares(&1000);
+    client.set_balance(&user, &1000);
+    stellar_asset_client.mint(&contract_id, &9000);
+
+    // Ten requests of 50 shares (250 assets) each, IDs 1..=10
+    for _ in 0..10 {
+        client.queue_withdraw(&user, &50);
+    }
+    assert_eq!(client.get_queue_length(), 10);
+
+    let page = client.get_pending_withdrawals(&4, &3);
+    assert_eq!(page.len(), 3);
+    assert_eq!(page.get(0).unwrap().id, 5);
+    assert_eq!(page.get(2).unwrap().id, 7);
+    assert_eq!(client.get_pending_withdrawals(&8, &5).len(), 2);
+
+    // Cancelled slots leave holes that pages skip
+    client.cancel_withdrawal(&6);
+    assert_eq!(client.get_pending_withdrawals(&4, &3).len(), 2);
+
+    // Cancelling the head moves it to the next live request
+    client.cancel_withdrawal(&1);
+    assert_eq!(client.get_queue_position(&2), Some(0));
+    assert_eq!(client.get_queue_length(), 9);
+
+    // Processing pays exactly `limit` requests and skips holes
+    assert_eq!(client.process_queued_withdrawals(&5), 5);
+    assert_eq!(client.get_queue_position(&8), Some(0));
+    assert_eq!(token_client.balance(&user), 1250);
+    assert_eq!(client.get_user_withdrawals(&user).len(), 3);
+    assert_eq!(client.balance(&user), 600);
+}
+
+#[test]
+fn test_cancelled_requests_cannot_stall_the_queue() {
+    let env = Env::default();
+    env.mock_all_auths_allowing_non_root_auth();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, token_client) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    client.set_withdraw_queue_threshold(&100);
 
-    // Try to queue another - should panic because user already has pending withdrawal
-    // This will try to withdraw 250 shares = 1250 assets, which is above threshold
-    client.queue_withdraw(&user, &250);
+    let victim = Address::generate(&env);
+    let griefer = Address::generate(&env);
+    stellar_asset_client.mint(&victim, &1000);
+    stellar_asset_client.mint(&griefer, &1000);
+    client.deposit(&victim, &1000);
+    client.deposit(&griefer, &1000);
+
+    // 200 cancelled requests leave empty slots between two live ones
+    client.queue_withdraw(&victim, &500);
+    for _ in 0..200 {
+        let id = client.queue_withdraw(&griefer, &200);
+        client.cancel_withdrawal(&id);
+    }
+    let last = client.queue_withdraw(&griefer, &200);
+
+    // Each call skips a bounded number of empty slots and the next carries on
+    assert_eq!(client.process_queued_withdrawals(&2), 1);
+    assert_eq!(token_client.balance(&victim), 500);
+    let mut calls = 1;
+    while client.get_withdrawal(&last).is_some() {
+        client.process_queued_withdrawals(&1);
+        calls += 1;
+    }
+    assert_eq!(calls, 7);
+    assert_eq!(token_client.balance(&griefer), 200);
+    assert_eq!(client.get_queue_length(), 0);
 }
 
 #[test]
@@ -1049,7 +1184,7 @@ fn test_process_withdraw_queue_empty() {
     let env = Env::default();
     env.mock_all_auths();
 
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
@@ -1069,7 +1204,7 @@ fn test_cancel_withdraw_not_found() {
     let env = Env::default();
     env.mock_all_auths_allowing_non_root_auth();
 
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
@@ -1094,7 +1229,7 @@ fn test_withdrawal_queue_fifo_order() {
     let token_admin = Address::generate(&env);
     let (token_id, stellar_asset_client, token_client) = create_token_contract(&env, &token_admin);
 
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
@@ -1109,16 +1244,15 @@ fn test_withdrawal_queue_fifo_order() {
     let user1 = Address::generate(&env);
     let user2 = Address::generate(&env);
     client.set_total_shares(&1000);
-    client.set_total_assets(&5000);
     client.set_balance(&user1, &300);
     client.set_balance(&user2, &300);
-    stellar_asset_client.mint(&contract_id, &5000);
+    stellar_asset_client.mint(&contract_id, &9000);
 
     // Queue withdrawals in order using queue_withdraw
     client.queue_withdraw(&user1, &300);
     client.queue_withdraw(&user2, &300);
 
-    let pending = client.get_pending_withdrawals();
+    let pending = client.get_pending_withdrawals(&0, &100);
     assert_eq!(pending.len(), 2);
     assert_eq!(pending.get(0).unwrap().user, user1);
     assert_eq!(pending.get(1).unwrap().user, user2);
@@ -1141,7 +1275,7 @@ fn test_withdrawal_queue_full_lifecycle() {
     let token_admin = Address::generate(&env);
     let (token_id, stellar_asset_client, token_client) = create_token_contract(&env, &token_admin);
 
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
@@ -1154,34 +1288,172 @@ fn test_withdrawal_queue_full_lifecycle() {
 
     let user = Address::generate(&env);
     client.set_total_shares(&1000);
-    client.set_total_assets(&5000);
     client.set_balance(&user, &500);
-    stellar_asset_client.mint(&contract_id, &5000);
+    stellar_asset_client.mint(&contract_id, &9000);
 
-    // 1. Queue withdrawal via queue_withdraw
+    // 1. Queue withdrawal via queue_withdraw; shares move into escrow
     client.queue_withdraw(&user, &300);
-    // Balance stays at 500 until the queue is processed
-    assert_eq!(client.balance(&user), 500);
-    assert_eq!(client.get_pending_withdrawals().len(), 1);
+    assert_eq!(client.balance(&user), 200);
+    assert_eq!(client.get_pending_withdrawals(&0, &100).len(), 1);
 
-    // 2. Cancel withdrawal - cancel_queued_withdrawal returns shares, so balance = 500 + 300 = 800
+    // 2. Cancel withdrawal - escrowed shares come back
     client.cancel_queued_withdrawal(&user);
-    assert_eq!(client.balance(&user), 800);
-    assert_eq!(client.get_pending_withdrawals().len(), 0);
+    assert_eq!(client.balance(&user), 500);
+    assert_eq!(client.get_pending_withdrawals(&0, &100).len(), 0);
 
-    // 3. Queue again (user has 800 shares now)
+    // 3. Queue again
     client.queue_withdraw(&user, &300);
-    assert_eq!(client.balance(&user), 800); // still 800 until processed
-    assert_eq!(client.get_pending_withdrawals().len(), 1);
+    assert_eq!(client.balance(&user), 200);
+    assert_eq!(client.get_pending_withdrawals(&0, &100).len(), 1);
 
-    // 4. Process withdrawal — process_queued_withdrawals does NOT reduce user balance,
-    // it only reduces total_shares/total_assets and transfers tokens.
-    // User balance stays at 800 (queue_withdraw doesn't deduct, cancel added 300 back).
+    // 4. Process withdrawal - escrowed shares are burned and assets paid
     client.process_queued_withdrawals(&1);
-    assert_eq!(client.balance(&user), 800);
+    assert_eq!(client.balance(&user), 200);
+    assert_eq!(client.balance(&contract_id), 0);
+    assert_eq!(client.total_shares(), 700);
     assert_eq!(token_client.balance(&user), 1500);
-    assert_eq!(client.get_pending_withdrawals().len(), 0);
+    assert_eq!(client.get_pending_withdrawals(&0, &100).len(), 0);
+}
+
+#[test]
+#[should_panic(expected = "insufficient shares for transfer")]
+fn test_escrowed_shares_cannot_be_transferred() {
+    let env = Env::default();
+    env.mock_all_auths_allowing_non_root_auth();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, _) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    client.set_withdraw_queue_threshold(&1000);
+
+    let user = Address::generate(&env);
+    client.set_total_shares(&1000);
+    client.set_balance(&user, &500);
+    stellar_asset_client.mint(&contract_id, &9000);
+
+    client.queue_withdraw(&user, &300);
+    client.transfer(&user, &Address::generate(&env), &300);
+}
+
+#[test]
+fn test_epoch_settles_all_requests_at_one_price() {
+    let env = Env::default();
+    env.mock_all_auths_allowing_non_root_auth();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, token_client) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    client.set_withdraw_queue_threshold(&1000);
+
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+    let user3 = Address::generate(&env);
+    client.set_total_shares(&1000);
+    client.set_balance(&user1, &300);
+    client.set_balance(&user2, &300);
+    client.set_balance(&user3, &300);
+    stellar_asset_client.mint(&contract_id, &9000);
+
+    // Two requests in epoch 1
+    assert_eq!(client.current_epoch(), 1);
+    client.queue_withdraw(&user1, &300);
+    let second = client.queue_withdraw(&user2, &300);
+    assert_eq!(client.get_epoch(&1).shares, 600);
+    assert_eq!(client.try_claim(&user1, &1), Err(Ok(Error::EpochNotSettled)));
+
+    // Price doubles before the epoch closes; both requests get the new price
+    set_vault_assets(&env, &token_id, &contract_id, 19000);
+    assert_eq!(client.close_epoch(), 1);
+    assert_eq!(client.current_epoch(), 2);
+    // 600 * (19000 + 1000) / (1000 + 1000) = 6000
+    let epoch = client.get_epoch(&1);
+    assert!(epoch.settled);
+    assert_eq!(epoch.assets, 6000);
+    assert_eq!(client.reserved_assets(), 6000);
+    assert_eq!(client.total_shares(), 400);
+    assert_eq!(client.total_assets(), 13000);
+
+    // Settled requests can no longer be cancelled
+    assert_eq!(client.try_cancel_withdrawal(&second), Err(Ok(Error::EpochClosed)));
+
+    // Requests made now fall into epoch 2
+    let third = client.queue_withdraw(&user3, &300);
+    assert_eq!(client.get_withdrawal(&third).unwrap().epoch, 2);
+
+    // Claims pay out, so they stop while the vault is paused
+    client.set_paused(&true);
+    assert!(client.try_claim(&user2, &1).is_err());
+    client.set_paused(&false);
+
+    assert_eq!(client.claim(&user2, &1), 3000);
+    assert_eq!(client.claim(&user1, &1), 3000);
+    assert_eq!(token_client.balance(&user1), 3000);
+    assert_eq!(token_client.balance(&user2), 3000);
+    assert_eq!(client.reserved_assets(), 0);
+    assert_eq!(client.try_claim(&user1, &1), Err(Ok(Error::WithdrawalNotFound)));
+    assert_eq!(client.get_queue_position(&third), Some(0));
+}
+
+#[test]
+fn test_close_epoch_raises_liquidity_from_strategies() {
+    let env = Env::default();
+    env.mock_all_auths_allowing_non_root_auth();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, token_client) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    let strategy_id = env.register(mock_strategy::MockStrategy, ());
+    let strategy = mock_strategy::MockStrategyClient::new(&env, &strategy_id);
+    strategy.initialize(&contract_id, &token_id);
+    client.propose_action(&admin, &ActionType::AddStrategy(strategy_id.clone()));
+
+    let user = Address::generate(&env);
+    stellar_asset_client.mint(&user, &1000);
+    client.deposit(&user, &1000);
+
+    // Strategy holds another 4000
+    stellar_asset_client.mint(&strategy_id, &4000);
+    strategy.simulate_price_drift(&4000);
+    assert_eq!(client.total_assets(), 5000);
 
+    client.set_withdraw_queue_threshold(&100);
+    client.queue_withdraw(&user, &600);
+
+    // 600 * (5000 + 1000) / (1000 + 1000) = 1800; 800 of it comes from the strategy
+    client.close_epoch();
+    assert_eq!(strategy.balance(), 3200);
+    assert_eq!(token_client.balance(&contract_id), 1800);
+    assert_eq!(client.total_assets(), 3200);
+
+    assert_eq!(client.claim(&user, &1), 1800);
+    assert_eq!(token_client.balance(&user), 1800);
 }
 // ── Oracle Allocation Validation Tests ─────────────────────────
 
@@ -1190,7 +1462,7 @@ fn test_valid_allocation_sum_to_100_percent() {
     let env = Env::default();
     env.mock_all_auths();
 
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
@@ -1220,7 +1492,7 @@ fn test_empty_allocation_accepted() {
     let env = Env::default();
     env.mock_all_auths();
 
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
@@ -1243,7 +1515,7 @@ fn test_allocation_sum_less_than_100_percent_rejected() {
     let env = Env::default();
     env.mock_all_auths();
 
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
@@ -1272,7 +1544,7 @@ fn test_allocation_sum_greater_than_100_percent_rejected() {
     let env = Env::default();
     env.mock_all_auths();
 
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
@@ -1303,7 +1575,7 @@ fn test_negative_allocation_rejected() {
     let env = Env::default();
     env.mock_all_auths();
 
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
@@ -1332,7 +1604,7 @@ fn test_single_strategy_100_percent_allocation() {
     let env = Env::default();
     env.mock_all_auths();
 
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
@@ -1358,7 +1630,7 @@ fn test_multiple_negative_allocations_rejected() {
     let env = Env::default();
     env.mock_all_auths();
 
-    let contract_id = env.register_contract(None, VolatilityShield);
+    let contract_id = env.register(VolatilityShield, ());
     let client = VolatilityShieldClient::new(&env, &contract_id);
 
     let admin = Address::generate(&env);
@@ -1380,3 +1652,1861 @@ fn test_multiple_negative_allocations_rejected() {
     let result = client.try_set_oracle_data(&allocations, &1000);
     assert_eq!(result, Err(Ok(Error::NegativeAllocation)));
 }
+
+// ── Share Token (SEP-41) Tests ─────────────────────────
+
+#[test]
+fn test_share_transfer_keeps_total_supply() {
+    let env = Env::default();
+    env.mock_all_auths_allowing_non_root_auth();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, _) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    stellar_asset_client.mint(&alice, &1000);
+    client.deposit(&alice, &1000);
+
+    client.transfer(&alice, &bob, &400);
+
+    assert_eq!(client.balance(&alice), 600);
+    assert_eq!(client.balance(&bob), 400);
+    assert_eq!(client.total_supply(), 1000);
+    assert_eq!(client.total_shares(), 1000);
+}
+
+#[test]
+#[should_panic(expected = "insufficient shares for transfer")]
+fn test_share_transfer_insufficient_balance() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &Address::generate(&env), &Address::generate(&env), &Address::generate(&env), &0u32, &guardians, &1u32);
+
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    client.set_balance(&alice, &100);
+
+    client.transfer(&alice, &bob, &101);
+}
+
+#[test]
+fn test_share_setters_require_admin() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &Address::generate(&env), &Address::generate(&env), &Address::generate(&env), &0u32, &guardians, &1u32);
+
+    // Without the admin's signature nobody can mint shares out of thin air
+    env.set_auths(&[]);
+    let attacker = Address::generate(&env);
+    assert!(client.try_set_balance(&attacker, &1_000_000).is_err());
+    assert!(client.try_set_total_shares(&1_000_000).is_err());
+    assert_eq!(client.balance(&attacker), 0);
+    assert_eq!(client.total_supply(), 0);
+}
+
+#[test]
+fn test_share_approve_and_transfer_from() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &Address::generate(&env), &Address::generate(&env), &Address::generate(&env), &0u32, &guardians, &1u32);
+
+    let alice = Address::generate(&env);
+    let spender = Address::generate(&env);
+    let bob = Address::generate(&env);
+    client.set_balance(&alice, &500);
+    client.set_total_shares(&500);
+
+    client.approve(&alice, &spender, &300, &200);
+    assert_eq!(client.allowance(&alice, &spender), 300);
+
+    client.transfer_from(&spender, &alice, &bob, &200);
+
+    assert_eq!(client.allowance(&alice, &spender), 100);
+    assert_eq!(client.balance(&alice), 300);
+    assert_eq!(client.balance(&bob), 200);
+    assert_eq!(client.total_supply(), 500);
+
+    // Allowance lapses once the expiration ledger has passed
+    env.ledger().set_sequence_number(201);
+    assert_eq!(client.allowance(&alice, &spender), 0);
+}
+
+#[test]
+#[should_panic(expected = "insufficient allowance")]
+fn test_share_transfer_from_exceeds_allowance() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &Address::generate(&env), &Address::generate(&env), &Address::generate(&env), &0u32, &guardians, &1u32);
+
+    let alice = Address::generate(&env);
+    let spender = Address::generate(&env);
+    client.set_balance(&alice, &500);
+
+    client.approve(&alice, &spender, &100, &200);
+    client.transfer_from(&spender, &alice, &spender, &101);
+}
+
+#[test]
+fn test_share_metadata() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, _, token_client) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    assert_eq!(client.decimals(), token_client.decimals());
+    assert_eq!(client.symbol(), String::from_str(&env, "xVS"));
+
+    client.set_share_metadata(
+        &String::from_str(&env, "XHedge NGN Shield"),
+        &String::from_str(&env, "xNGN"),
+    );
+    assert_eq!(client.name(), String::from_str(&env, "XHedge NGN Shield"));
+    assert_eq!(client.symbol(), String::from_str(&env, "xNGN"));
+}
+
+// ── Preview & Max Tests ─────────────────────────
+
+#[test]
+fn test_preview_functions_round_in_favor_of_vault() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, _) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    // With the virtual offset the price is (3330 + 1000) / (299 + 1000) = 10/3
+    stellar_asset_client.mint(&contract_id, &3330);
+    client.set_total_shares(&299);
+
+    // 100 assets = 30 shares exactly
+    assert_eq!(client.preview_deposit(&100), 30);
+    assert_eq!(client.preview_withdraw(&100), 30);
+
+    // 101 assets = 30.3 shares: deposit rounds down, withdraw rounds up
+    assert_eq!(client.preview_deposit(&101), 30);
+    assert_eq!(client.preview_withdraw(&101), 31);
+
+    // 100 shares = 333.33 assets: redeem rounds down, mint rounds up
+    assert_eq!(client.preview_redeem(&100), 333);
+    assert_eq!(client.preview_mint(&100), 334);
+}
+
+#[test]
+fn test_max_functions_respect_caps_and_pause() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, _) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    let user = Address::generate(&env);
+    // Price is (3000 + 1000) / (1000 + 1000) = 2 assets per share
+    stellar_asset_client.mint(&contract_id, &3000);
+    client.set_total_shares(&1000);
+    client.set_balance(&user, &400);
+
+    // No caps configured
+    assert_eq!(client.max_deposit(&user), i128::MAX);
+    assert_eq!(client.max_mint(&user), i128::MAX);
+    assert_eq!(client.max_withdraw(&user), 800);
+    assert_eq!(client.max_redeem(&user), 400);
+
+    // Per-user cap of 500 shares leaves 100 shares (200 assets) of headroom;
+    // global cap of 3100 assets leaves 100 assets (50 shares).
+    client.set_deposit_cap(&500, &3100);
+    assert_eq!(client.max_deposit(&user), 100);
+    assert_eq!(client.max_mint(&user), 50);
+
+    // Per-tx cap and queue threshold bound instant withdrawals
+    client.set_withdraw_cap(&600);
+    client.set_withdraw_queue_threshold(&500);
+    assert_eq!(client.max_withdraw(&user), 500);
+    assert_eq!(client.max_redeem(&user), 250);
+
+    client.set_paused(&true);
+    assert_eq!(client.max_deposit(&user), 0);
+    assert_eq!(client.max_mint(&user), 0);
+    assert_eq!(client.max_withdraw(&user), 0);
+    assert_eq!(client.max_redeem(&user), 0);
+}
+
+// ── Mint / Withdraw-Assets Tests ─────────────────────────
+
+#[test]
+fn test_mint_pulls_assets_rounded_up() {
+    let env = Env::default();
+    env.mock_all_auths_allowing_non_root_auth();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, token_client) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    stellar_asset_client.mint(&contract_id, &3330);
+    client.set_total_shares(&299);
+
+    let payer = Address::generate(&env);
+    let receiver = Address::generate(&env);
+    stellar_asset_client.mint(&payer, &1000);
+
+    // 100 shares are worth 333.33 assets, so the payer is charged 334
+    let paid = client.mint(&payer, &receiver, &100);
+
+    assert_eq!(paid, 334);
+    assert_eq!(token_client.balance(&payer), 666);
+    assert_eq!(client.balance(&receiver), 100);
+    assert_eq!(client.balance(&payer), 0);
+    assert_eq!(client.total_shares(), 399);
+    assert_eq!(client.total_assets(), 3664);
+}
+
+#[test]
+fn test_withdraw_assets_burns_shares_rounded_up() {
+    let env = Env::default();
+    env.mock_all_auths_allowing_non_root_auth();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, token_client) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    client.set_total_shares(&299);
+    stellar_asset_client.mint(&contract_id, &3330);
+
+    let owner = Address::generate(&env);
+    let receiver = Address::generate(&env);
+    client.set_balance(&owner, &299);
+
+    // 101 assets are worth 30.3 shares, so 31 shares are burned
+    let burned = client.withdraw_assets(&owner, &receiver, &101);
+
+    assert_eq!(burned, 31);
+    assert_eq!(client.balance(&owner), 268);
+    assert_eq!(token_client.balance(&receiver), 101);
+    assert_eq!(token_client.balance(&owner), 0);
+    assert_eq!(client.total_shares(), 268);
+    assert_eq!(client.total_assets(), 3229);
+}
+
+#[test]
+fn test_deposit_for_and_redeem_on_behalf() {
+    let env = Env::default();
+    env.mock_all_auths_allowing_non_root_auth();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, token_client) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    let parent = Address::generate(&env);
+    let child = Address::generate(&env);
+    stellar_asset_client.mint(&parent, &1000);
+
+    client.deposit_for(&parent, &child, &1000);
+    assert_eq!(client.balance(&parent), 0);
+    assert_eq!(client.balance(&child), 1000);
+
+    let paid = client.redeem(&child, &parent, &400);
+    assert_eq!(paid, 400);
+    assert_eq!(client.balance(&child), 600);
+    assert_eq!(token_client.balance(&parent), 400);
+}
+
+#[test]
+#[should_panic(expected = "withdrawal exceeds queue threshold")]
+fn test_withdraw_assets_above_threshold_rejected() {
+    let env = Env::default();
+    env.mock_all_auths_allowing_non_root_auth();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, _) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    client.set_withdraw_queue_threshold(&1000);
+    stellar_asset_client.mint(&contract_id, &5000);
+    client.set_total_shares(&5000);
+
+    let owner = Address::generate(&env);
+    client.set_balance(&owner, &5000);
+    client.withdraw_assets(&owner, &owner, &1001);
+}
+
+// ── Share Inflation Property Tests ─────────────────────────
+
+mod inflation_attack_props {
+    use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn prop_donation_cannot_zero_out_next_depositor(
+            attacker_deposit in 1i128..1_000,
+            donation in 0i128..1_000_000_000_000,
+            victim_deposit in 1i128..1_000_000_000_000,
+        ) {
+            let env = Env::default();
+            env.mock_all_auths_allowing_non_root_auth();
+
+            let token_admin = Address::generate(&env);
+            let (token_id, stellar_asset_client, token_client) = create_token_contract(&env, &token_admin);
+
+            let contract_id = env.register(VolatilityShield, ());
+            let client = VolatilityShieldClient::new(&env, &contract_id);
+
+            let admin = Address::generate(&env);
+            let oracle = Address::generate(&env);
+            let treasury = Address::generate(&env);
+            let guardians = soroban_sdk::vec![&env, admin.clone()];
+            client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+            // Attacker seeds the empty vault, then donates directly to it.
+            // NAV is read from real balances, so the donation raises the price.
+            let attacker = Address::generate(&env);
+            stellar_asset_client.mint(&attacker, &(attacker_deposit + donation));
+            client.deposit(&attacker, &attacker_deposit);
+            if donation > 0 {
+                token_client.transfer(&attacker, &contract_id, &donation);
+            }
+
+            let victim = Address::generate(&env);
+            stellar_asset_client.mint(&victim, &victim_deposit);
+            let result = client.try_deposit(&victim, &victim_deposit);
+
+            // Any deposit worth a thousandth of the vault succeeds with shares,
+            // so blocking a deposit costs the attacker ~1000x its size
+            if victim_deposit * VIRTUAL_SHARES >= attacker_deposit + donation + VIRTUAL_ASSETS {
+                prop_assert!(result.is_ok());
+                prop_assert!(client.balance(&victim) > 0);
+            }
+            if result.is_ok() {
+                prop_assert!(client.balance(&victim) > 0);
+            } else {
+                // Smaller deposits may revert, and the victim keeps their tokens
+                prop_assert_eq!(token_client.balance(&victim), victim_deposit);
+            }
+
+            // The attacker can never redeem more than they put in
+            let attacker_value = client.convert_to_assets(&client.balance(&attacker));
+            prop_assert!(attacker_value <= attacker_deposit + donation);
+        }
+
+        #[test]
+        fn prop_round_trip_never_exceeds_input(
+            total_shares in 0i128..1_000_000_000_000_000_000,
+            total_assets in 0i128..1_000_000_000_000_000_000,
+            amount in 0i128..1_000_000_000_000_000_000,
+        ) {
+            let env = Env::default();
+            env.mock_all_auths();
+
+            let token_admin = Address::generate(&env);
+            let (token_id, _, _) = create_token_contract(&env, &token_admin);
+
+            let contract_id = env.register(VolatilityShield, ());
+            let client = VolatilityShieldClient::new(&env, &contract_id);
+
+            let admin = Address::generate(&env);
+            let guardians = soroban_sdk::vec![&env, admin.clone()];
+            client.init(&admin, &token_id, &admin, &admin, &0u32, &guardians, &1u32);
+
+            client.set_total_shares(&total_shares);
+            set_vault_assets(&env, &token_id, &contract_id, total_assets);
+
+            let shares = client.convert_to_shares(&amount);
+            prop_assert!(client.convert_to_assets(&shares) <= amount);
+
+            let assets = client.convert_to_assets(&amount);
+            prop_assert!(client.convert_to_shares(&assets) <= amount);
+        }
+    }
+}
+
+// ── NAV Tests ─────────────────────────
+
+#[test]
+fn test_total_assets_reads_real_balances() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, _) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    let strategy_id = env.register(mock_strategy::MockStrategy, ());
+    let strategy = mock_strategy::MockStrategyClient::new(&env, &strategy_id);
+    client.propose_action(&admin, &ActionType::AddStrategy(strategy_id.clone()));
+
+    stellar_asset_client.mint(&contract_id, &1000);
+    strategy.simulate_price_drift(&500);
+    assert_eq!(client.total_assets(), 1500);
+
+    // An untracked strategy loss is reflected immediately
+    strategy.simulate_price_drift(&200);
+    assert_eq!(client.total_assets(), 1200);
+}
+
+#[test]
+fn test_repeated_harvest_does_not_inflate_assets() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, _, _) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    let strategy_id = env.register(mock_strategy::MockStrategy, ());
+    let strategy = mock_strategy::MockStrategyClient::new(&env, &strategy_id);
+    client.propose_action(&admin, &ActionType::AddStrategy(strategy_id.clone()));
+    strategy.simulate_price_drift(&1000);
+
+    assert_eq!(client.harvest(), 1000);
+    assert_eq!(client.get_strategy_debt(&strategy_id), 1000);
+    assert_eq!(client.total_assets(), 1000);
+
+    // Nothing new was earned, so nothing is reported again
+    assert_eq!(client.harvest(), 0);
+    assert_eq!(client.total_assets(), 1000);
+
+    strategy.simulate_price_drift(&1100);
+    assert_eq!(client.harvest(), 100);
+    assert_eq!(client.total_assets(), 1100);
+}
+
+#[test]
+fn test_nav_snapshot_cache() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, _) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    let strategy_id = env.register(mock_strategy::MockStrategy, ());
+    let strategy = mock_strategy::MockStrategyClient::new(&env, &strategy_id);
+    client.propose_action(&admin, &ActionType::AddStrategy(strategy_id.clone()));
+
+    env.ledger().set_timestamp(1000);
+    stellar_asset_client.mint(&contract_id, &1000);
+    strategy.simulate_price_drift(&500);
+
+    client.set_nav_max_age(&60);
+    assert_eq!(client.update_nav(), 1500);
+    assert_eq!(client.get_nav_snapshot().unwrap().strategy_assets, 500);
+
+    // Strategy value is served from the snapshot while it is fresh,
+    // but idle balance is always live
+    strategy.simulate_price_drift(&800);
+    stellar_asset_client.mint(&contract_id, &100);
+    assert_eq!(client.total_assets(), 1600);
+
+    // Once the snapshot is stale, strategies are read live again
+    env.ledger().set_timestamp(1061);
+    assert_eq!(client.total_assets(), 1900);
+}
+
+// ── Fee Tests ─────────────────────────
+
+#[test]
+fn test_harvest_mints_performance_fee_shares_to_treasury() {
+    let env = Env::default();
+    env.mock_all_auths_allowing_non_root_auth();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, _) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &1000u32, &guardians, &1u32);
+
+    let strategy_id = env.register(mock_strategy::MockStrategy, ());
+    let strategy = mock_strategy::MockStrategyClient::new(&env, &strategy_id);
+    client.propose_action(&admin, &ActionType::AddStrategy(strategy_id.clone()));
+
+    let user = Address::generate(&env);
+    stellar_asset_client.mint(&user, &1000);
+    client.deposit(&user, &1000);
+
+    // Strategy earns 1000; a 10% fee is 100 assets
+    strategy.simulate_price_drift(&1000);
+    assert_eq!(client.harvest(), 1000);
+
+    // 100 * (1000 + 1000) / (2000 + 1000 - 100) = 68.97 shares
+    assert_eq!(client.balance(&treasury), 68);
+    assert_eq!(client.total_shares(), 1068);
+    assert_eq!(client.accrued_fees(), 100);
+    assert_eq!(client.total_assets(), 2000);
+
+    // No new yield, no new fee
+    client.harvest();
+    assert_eq!(client.balance(&treasury), 68);
+    assert_eq!(client.accrued_fees(), 100);
+}
+
+#[test]
+fn test_fee_setters() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let asset = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &asset, &oracle, &treasury, &500u32, &guardians, &1u32);
+
+    client.set_fee_percentage(&2000u32);
+    assert_eq!(client.fee_percentage(), 2000u32);
+
+    let result = client.try_set_fee_percentage(&10_001u32);
+    assert_eq!(result, Err(Ok(Error::InvalidFeeRate)));
+
+    let new_treasury = Address::generate(&env);
+    client.set_treasury(&new_treasury);
+    assert_eq!(client.treasury(), new_treasury);
+}
+
+#[test]
+fn test_performance_fee_respects_high_water_mark() {
+    let env = Env::default();
+    env.mock_all_auths_allowing_non_root_auth();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, _) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &1000u32, &guardians, &1u32);
+
+    let strategy_id = env.register(mock_strategy::MockStrategy, ());
+    let strategy = mock_strategy::MockStrategyClient::new(&env, &strategy_id);
+    client.propose_action(&admin, &ActionType::AddStrategy(strategy_id.clone()));
+
+    let user = Address::generate(&env);
+    stellar_asset_client.mint(&user, &1000);
+    client.deposit(&user, &1000);
+    assert_eq!(client.high_water_mark(), PRICE_PRECISION);
+
+    // Gain to a new peak: fee charged and the mark moves up
+    strategy.simulate_price_drift(&1000);
+    client.harvest();
+    assert_eq!(client.accrued_fees(), 100);
+    let peak = client.high_water_mark();
+    assert!(peak > PRICE_PRECISION);
+    assert_eq!(peak, client.price_per_share());
+
+    // Currency shock: loss, no fee, mark unchanged
+    strategy.simulate_price_drift(&500);
+    assert_eq!(client.harvest(), -500);
+    assert_eq!(client.accrued_fees(), 100);
+    assert_eq!(client.high_water_mark(), peak);
+
+    // Recovery back to the previous peak is not charged again
+    strategy.simulate_price_drift(&1000);
+    assert_eq!(client.harvest(), 500);
+    assert_eq!(client.accrued_fees(), 100);
+    assert_eq!(client.high_water_mark(), peak);
+
+    // Only the gain above the peak is charged: 10% of 200
+    strategy.simulate_price_drift(&1200);
+    assert_eq!(client.harvest(), 200);
+    assert_eq!(client.accrued_fees(), 120);
+    assert!(client.high_water_mark() > peak);
+}
+
+#[test]
+fn test_management_fee_streams_to_treasury() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, _) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    assert_eq!(
+        client.try_set_management_fee(&501u32),
+        Err(Ok(Error::InvalidFeeRate))
+    );
+    client.set_management_fee(&200u32);
+    assert_eq!(client.management_fee(), 200u32);
+
+    let user = Address::generate(&env);
+    stellar_asset_client.mint(&user, &2000);
+    client.deposit(&user, &1000);
+    assert_eq!(client.pending_management_fee(), 0);
+
+    // One year at 2% of 1000 assets
+    env.ledger().with_mut(|li| li.timestamp += SECONDS_PER_YEAR);
+    assert_eq!(client.pending_management_fee(), 20);
+
+    // Quotes already account for the fee shares about to be minted
+    let quoted = client.preview_deposit(&1000);
+    client.deposit(&user, &1000);
+    assert_eq!(client.balance(&user), 1000 + quoted);
+
+    // 20 * (1000 + 1) / (1000 + 1 - 20) = 20.4 shares
+    assert_eq!(client.balance(&treasury), 20);
+    assert_eq!(client.accrued_fees(), 20);
+    assert_eq!(client.pending_management_fee(), 0);
+}
+
+#[test]
+fn test_entry_and_exit_fees_with_exemption_and_holding_period() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, token_client) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    assert_eq!(
+        client.try_set_entry_exit_fees(&10_000u32, &0u32, &0u64),
+        Err(Ok(Error::InvalidFeeRate))
+    );
+    client.set_entry_exit_fees(&100u32, &200u32, &1000u64);
+
+    let user = Address::generate(&env);
+    let partner = Address::generate(&env);
+    stellar_asset_client.mint(&user, &1000);
+    stellar_asset_client.mint(&partner, &1000);
+    client.set_fee_exempt(&partner, &true);
+    assert!(client.is_fee_exempt(&partner));
+
+    // 1% entry fee goes to the treasury
+    assert_eq!(client.preview_deposit(&1000), 990);
+    client.deposit(&user, &1000);
+    assert_eq!(client.balance(&user), 990);
+    assert_eq!(token_client.balance(&treasury), 10);
+
+    // Exempt partners deposit fee-free
+    client.deposit(&partner, &1000);
+    assert_eq!(client.balance(&partner), 1000);
+    assert_eq!(token_client.balance(&treasury), 10);
+
+    // 2% exit fee inside the holding period
+    assert_eq!(client.preview_redeem(&495), 485);
+    assert_eq!(client.redeem(&user, &user, &495), 485);
+    assert_eq!(token_client.balance(&treasury), 20);
+
+    // Waived once the holding period has passed
+    env.ledger().with_mut(|li| li.timestamp += 1000);
+    assert_eq!(client.redeem(&user, &user, &495), 495);
+    assert_eq!(token_client.balance(&user), 980);
+    assert_eq!(token_client.balance(&treasury), 20);
+    assert_eq!(client.accrued_fees(), 20);
+}
+
+#[test]
+fn test_dust_transfer_does_not_restart_holding_period() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, token_client) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+    client.set_entry_exit_fees(&0u32, &200u32, &1000u64);
+
+    let victim = Address::generate(&env);
+    let griefer = Address::generate(&env);
+    stellar_asset_client.mint(&victim, &1000);
+    stellar_asset_client.mint(&griefer, &1001);
+    client.deposit(&victim, &1000);
+    env.ledger().with_mut(|li| li.timestamp += 5000);
+    client.deposit(&griefer, &1000);
+
+    // One share of dust barely moves the victim's clock
+    client.transfer(&griefer, &victim, &1);
+    client.deposit_for(&griefer, &victim, &1);
+    assert_eq!(client.balance(&victim), 1002);
+    assert_eq!(client.redeem(&victim, &victim, &1002), 1002);
+    assert_eq!(token_client.balance(&treasury), 0);
+
+    // Shares sent to an empty account start a fresh holding period
+    client.transfer(&griefer, &victim, &998);
+    assert_eq!(client.preview_redeem(&998), 978);
+    assert_eq!(client.max_withdraw(&victim), 978);
+}
+
+#[test]
+fn test_partial_fill_when_liquidity_is_short() {
+    let env = Env::default();
+    env.mock_all_auths_allowing_non_root_auth();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, token_client) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    let strategy_id = env.register(mock_strategy::MockStrategy, ());
+    let strategy = mock_strategy::MockStrategyClient::new(&env, &strategy_id);
+    strategy.initialize(&contract_id, &token_id);
+    client.propose_action(&admin, &ActionType::AddStrategy(strategy_id.clone()));
+
+    let user = Address::generate(&env);
+    stellar_asset_client.mint(&user, &1000);
+    client.deposit(&user, &1000);
+    stellar_asset_client.mint(&strategy_id, &8000);
+    strategy.simulate_price_drift(&8000);
+
+    // No strategy may be drawn on, so only the idle 1000 is available
+    client.set_withdrawal_order(&soroban_sdk::vec![&env]);
+    assert_eq!(
+        client.try_set_withdrawal_order(&soroban_sdk::vec![&env, Address::generate(&env)]),
+        Err(Ok(Error::NotInitialized))
+    );
+
+    client.set_withdraw_queue_threshold(&100);
+    let id = client.queue_withdraw(&user, &600);
+
+    // 3000 owed, 1000 paid; ceil(600 * 1000 / 3000) = 200 shares settled
+    assert_eq!(client.process_queued_withdrawals(&1), 0);
+    assert_eq!(token_client.balance(&user), 1000);
+    assert_eq!(client.get_withdrawal(&id).unwrap().shares, 400);
+    assert_eq!(client.reserved_assets(), 2000);
+    assert_eq!(client.total_assets(), 6000);
+
+    // Allow processing to pull the rest from the strategy
+    client.set_withdrawal_order(&soroban_sdk::vec![&env, strategy_id.clone()]);
+    client.set_queue_pulls_liquidity(&true);
+    assert_eq!(client.process_queued_withdrawals(&1), 1);
+    assert_eq!(token_client.balance(&user), 3000);
+    assert_eq!(strategy.balance(), 6000);
+    assert_eq!(client.reserved_assets(), 0);
+    assert_eq!(client.get_withdrawal(&id), None);
+}
+
+#[test]
+fn test_partial_fill_never_settles_zero_shares() {
+    let env = Env::default();
+    env.mock_all_auths_allowing_non_root_auth();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, token_client) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    let strategy_id = env.register(mock_strategy::MockStrategy, ());
+    let strategy = mock_strategy::MockStrategyClient::new(&env, &strategy_id);
+    strategy.initialize(&contract_id, &token_id);
+    client.propose_action(&admin, &ActionType::AddStrategy(strategy_id.clone()));
+    client.set_withdrawal_order(&soroban_sdk::vec![&env]);
+
+    let user = Address::generate(&env);
+    client.set_total_shares(&1000);
+    client.set_balance(&user, &1000);
+    stellar_asset_client.mint(&contract_id, &3);
+    stellar_asset_client.mint(&strategy_id, &8000);
+    strategy.simulate_price_drift(&8000);
+
+    // 4 owed but only 3 idle: a partial fill would settle no shares, so nothing is paid
+    client.set_withdraw_queue_threshold(&1);
+    let id = client.queue_withdraw(&user, &1);
+    assert_eq!(client.process_queued_withdrawals(&1), 0);
+    assert_eq!(token_client.balance(&user), 0);
+    assert_eq!(client.get_withdrawal(&id).unwrap().shares, 1);
+    assert_eq!(client.reserved_assets(), 4);
+
+    client.set_withdrawal_order(&soroban_sdk::vec![&env, strategy_id.clone()]);
+    client.set_queue_pulls_liquidity(&true);
+    assert_eq!(client.process_queued_withdrawals(&1), 1);
+    assert_eq!(token_client.balance(&user), 4);
+    assert_eq!(client.reserved_assets(), 0);
+}
+
+#[test]
+fn test_withdraw_pulls_from_strategies_and_bears_realised_loss() {
+    let env = Env::default();
+    env.mock_all_auths_allowing_non_root_auth();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, token_client) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    let liquid_id = env.register(mock_strategy::MockStrategy, ());
+    let liquid = mock_strategy::MockStrategyClient::new(&env, &liquid_id);
+    let lossy_id = env.register(mock_strategy::MockStrategy, ());
+    let lossy = mock_strategy::MockStrategyClient::new(&env, &lossy_id);
+    liquid.initialize(&contract_id, &token_id);
+    lossy.initialize(&contract_id, &token_id);
+    client.propose_action(&admin, &ActionType::AddStrategy(liquid_id.clone()));
+    client.propose_action(&admin, &ActionType::AddStrategy(lossy_id.clone()));
+
+    let user = Address::generate(&env);
+    stellar_asset_client.mint(&user, &1000);
+    client.deposit(&user, &1000);
+
+    // `liquid` holds what it reports; `lossy` reports 7000 but holds 500
+    stellar_asset_client.mint(&liquid_id, &1000);
+    liquid.simulate_price_drift(&1000);
+    stellar_asset_client.mint(&lossy_id, &500);
+    lossy.simulate_price_drift(&7000);
+    assert_eq!(client.total_assets(), 9000);
+
+    // 400 * (9000 + 1000) / (1000 + 1000) = 2000: 1000 idle plus 1000 from `liquid`
+    assert_eq!(client.redeem(&user, &user, &400), 2000);
+    assert_eq!(liquid.balance(), 0);
+
+    // 300 * (7000 + 1000) / (600 + 1000) = 1500, all asked of `lossy`,
+    // which only hands back 500
+    assert_eq!(client.redeem(&user, &user, &300), 500);
+    assert_eq!(token_client.balance(&user), 2500);
+    assert_eq!(lossy.balance(), 5500);
+
+    // Remaining holders keep their value
+    assert_eq!(client.total_assets(), 5500);
+    assert_eq!(client.total_shares(), 300);
+}
+
+#[test]
+#[should_panic(expected = "insufficient liquidity for withdrawal")]
+fn test_withdraw_fails_when_strategies_cannot_cover() {
+    let env = Env::default();
+    env.mock_all_auths_allowing_non_root_auth();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, _) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    let strategy_id = env.register(mock_strategy::MockStrategy, ());
+    let strategy = mock_strategy::MockStrategyClient::new(&env, &strategy_id);
+    strategy.initialize(&contract_id, &token_id);
+    client.propose_action(&admin, &ActionType::AddStrategy(strategy_id.clone()));
+
+    let user = Address::generate(&env);
+    stellar_asset_client.mint(&user, &1000);
+    client.deposit(&user, &1000);
+    stellar_asset_client.mint(&strategy_id, &1000);
+    strategy.simulate_price_drift(&1000);
+
+    // The strategy is left out of the withdrawal order
+    client.set_withdrawal_order(&soroban_sdk::vec![&env]);
+    client.redeem(&user, &user, &1000);
+}
+
+#[test]
+fn test_keeper_processes_due_withdrawals_for_a_bounty() {
+    let env = Env::default();
+    env.mock_all_auths_allowing_non_root_auth();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, token_client) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    assert_eq!(
+        client.try_set_keeper_config(&3600u64, &101u32, &600u64),
+        Err(Ok(Error::InvalidFeeRate))
+    );
+    client.set_withdraw_queue_threshold(&1000);
+
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+    client.set_total_shares(&1000);
+    client.set_balance(&user1, &300);
+    client.set_balance(&user2, &300);
+    stellar_asset_client.mint(&contract_id, &9000);
+    client.queue_withdraw(&user1, &300);
+    client.queue_withdraw(&user2, &300);
+
+    // Keepers are disabled until the admin configures them
+    let keeper = Address::generate(&env);
+    assert_eq!(client.get_keeper_config(), None);
+    assert_eq!(
+        client.try_process_due_withdrawals(&keeper, &10),
+        Err(Ok(Error::KeeperDisabled))
+    );
+    client.set_keeper_config(&3600u64, &50u32, &600u64);
+
+    // Nothing is due yet, so the open epoch stays open; an empty run does
+    // not lock other keepers out
+    assert_eq!(client.process_due_withdrawals(&keeper, &0), 0);
+    assert_eq!(client.process_due_withdrawals(&keeper, &10), 0);
+    assert_eq!(client.current_epoch(), 1);
+
+    // After the delay anyone can close the epoch and process; 0.5% of each
+    // 1500 payout is the bounty
+    env.ledger().with_mut(|li| li.timestamp += 3600);
+    assert_eq!(client.process_due_withdrawals(&keeper, &1), 1);
+    assert_eq!(client.current_epoch(), 2);
+    assert_eq!(
+        client.try_process_due_withdrawals(&keeper, &10),
+        Err(Ok(Error::RateLimited))
+    );
+    env.ledger().with_mut(|li| li.timestamp += 600);
+    assert_eq!(client.process_due_withdrawals(&keeper, &10), 1);
+    assert_eq!(token_client.balance(&user1), 1493);
+    assert_eq!(token_client.balance(&user2), 1493);
+    assert_eq!(token_client.balance(&keeper), 14);
+    assert_eq!(client.get_queue_length(), 0);
+}
+
+#[test]
+fn test_keeper_partial_fill_starts_the_rate_limit() {
+    let env = Env::default();
+    env.mock_all_auths_allowing_non_root_auth();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, token_client) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    let strategy_id = env.register(mock_strategy::MockStrategy, ());
+    let strategy = mock_strategy::MockStrategyClient::new(&env, &strategy_id);
+    strategy.initialize(&contract_id, &token_id);
+    client.propose_action(&admin, &ActionType::AddStrategy(strategy_id.clone()));
+    client.set_withdrawal_order(&soroban_sdk::vec![&env]);
+
+    let user = Address::generate(&env);
+    stellar_asset_client.mint(&user, &1000);
+    client.deposit(&user, &1000);
+    stellar_asset_client.mint(&strategy_id, &8000);
+    strategy.simulate_price_drift(&8000);
+
+    client.set_withdraw_queue_threshold(&100);
+    client.queue_withdraw(&user, &600);
+    client.close_epoch();
+    client.set_keeper_config(&0u64, &50u32, &3600u64);
+
+    // Only the idle 1000 of the 3000 owed is paid, but the keeper still earned a bounty
+    let keeper = Address::generate(&env);
+    assert_eq!(client.process_due_withdrawals(&keeper, &1), 0);
+    assert_eq!(token_client.balance(&keeper), 5);
+    assert_eq!(
+        client.try_process_due_withdrawals(&keeper, &1),
+        Err(Ok(Error::RateLimited))
+    );
+    assert_eq!(token_client.balance(&keeper), 5);
+}
+
+#[test]
+fn test_transferred_withdrawal_claim_pays_new_owner() {
+    let env = Env::default();
+    env.mock_all_auths_allowing_non_root_auth();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, token_client) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    client.set_withdraw_queue_threshold(&1000);
+    client.set_entry_exit_fees(&0u32, &200u32, &1000u64);
+
+    let seller = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    client.set_fee_exempt(&buyer, &true);
+    client.set_total_shares(&1000);
+    client.set_balance(&seller, &600);
+    stellar_asset_client.mint(&contract_id, &9000);
+
+    let sold = client.queue_withdraw(&seller, &300);
+    let gifted = client.queue_withdraw(&seller, &300);
+    client.transfer_withdrawal_claim(&gifted, &buyer);
+    client.transfer_withdrawal_claim(&sold, &buyer);
+
+    let claim = client.get_withdrawal(&sold).unwrap();
+    assert_eq!(claim.user, seller);
+    assert_eq!(claim.owner, buyer);
+    assert_eq!(client.get_user_withdrawals(&seller).len(), 0);
+    // The buyer's claims stay in queue order
+    let held = client.get_user_withdrawals(&buyer);
+    assert_eq!(held.get(0).unwrap().id, sold);
+    assert_eq!(held.get(1).unwrap().id, gifted);
+    assert_eq!(
+        client.try_transfer_withdrawal_claim(&99, &buyer),
+        Err(Ok(Error::WithdrawalNotFound))
+    );
+
+    // Cancelling returns the escrowed shares to the current owner
+    client.cancel_withdrawal(&gifted);
+    assert_eq!(client.balance(&buyer), 300);
+    assert_eq!(client.balance(&seller), 0);
+
+    // The payout goes to the current owner, less the seller's exit fee
+    client.process_queued_withdrawals(&1);
+    assert_eq!(token_client.balance(&buyer), 1470);
+    assert_eq!(token_client.balance(&treasury), 30);
+    assert_eq!(token_client.balance(&seller), 0);
+}
+
+#[test]
+fn test_sweep_expired_withdrawals() {
+    let env = Env::default();
+    env.mock_all_auths_allowing_non_root_auth();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, token_client) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    client.set_withdraw_queue_threshold(&1000);
+
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+    client.set_total_shares(&1000);
+    client.set_balance(&user1, &300);
+    client.set_balance(&user2, &300);
+    stellar_asset_client.mint(&contract_id, &9000);
+
+    client.queue_withdraw(&user1, &300);
+    env.ledger().with_mut(|li| li.timestamp += 500);
+    client.queue_withdraw(&user2, &300);
+
+    // Expiry is disabled by default
+    env.ledger().with_mut(|li| li.timestamp += 500);
+    assert_eq!(client.sweep_expired_withdrawals(&10), 0);
+
+    // Only the first request is old enough to be cancelled
+    client.set_queue_expiry(&1000u64, &ExpiryPolicy::Cancel);
+    assert_eq!(client.sweep_expired_withdrawals(&10), 1);
+    assert_eq!(client.balance(&user1), 300);
+    assert_eq!(client.get_user_withdrawals(&user1).len(), 0);
+    assert_eq!(client.get_user_withdrawals(&user2).len(), 1);
+
+    // With the process policy the second one is paid out once it expires
+    client.set_queue_expiry(&1000u64, &ExpiryPolicy::Process);
+    env.ledger().with_mut(|li| li.timestamp += 500);
+    assert_eq!(client.sweep_expired_withdrawals(&10), 1);
+    assert_eq!(token_client.balance(&user2), 1500);
+    assert_eq!(client.get_queue_length(), 0);
+}
+
+#[test]
+fn test_express_lane_skips_queue_for_a_fee_left_in_vault() {
+    let env = Env::default();
+    env.mock_all_auths_allowing_non_root_auth();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, token_client) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    client.set_withdraw_queue_threshold(&1000);
+
+    let whale = Address::generate(&env);
+    let holder = Address::generate(&env);
+    client.set_total_shares(&1000);
+    client.set_balance(&whale, &500);
+    client.set_balance(&holder, &500);
+    stellar_asset_client.mint(&contract_id, &9000);
+
+    assert_eq!(
+        client.try_set_express_exit_fee(&10_000u32),
+        Err(Ok(Error::InvalidFeeRate))
+    );
+    client.set_express_exit_fee(&200u32);
+    assert_eq!(client.max_withdraw(&whale), 2450);
+
+    // 300 shares = 1500 assets, above the threshold: 2% (30) stays in the vault
+    assert_eq!(client.preview_redeem(&300), 1470);
+    assert_eq!(client.redeem(&whale, &whale, &300), 1470);
+    assert_eq!(token_client.balance(&whale), 1470);
+    assert_eq!(client.get_queue_length(), 0);
+    assert_eq!(client.total_assets(), 7530);
+
+    // Exact-asset withdrawals gross up for the fee; below the threshold there is none
+    let shares = client.preview_withdraw(&1200);
+    assert_eq!(client.withdraw_assets(&holder, &holder, &1200), shares);
+    let below_threshold = client.preview_withdraw(&500);
+
+    // Remaining holders gained the express fees
+    assert!(client.convert_to_assets(&100) > 500);
+
+    client.set_express_exit_fee(&0u32);
+    assert_eq!(client.preview_withdraw(&500), below_threshold);
+}
+
+#[test]
+fn test_harvest_uses_strategy_reported_profit_and_loss() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, _, _) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    let strategy_id = env.register(mock_strategy::MockStrategy, ());
+    let mock = mock_strategy::MockStrategyClient::new(&env, &strategy_id);
+    mock.initialize(&contract_id, &token_id);
+    client.propose_action(&admin, &ActionType::AddStrategy(strategy_id.clone()));
+
+    // The mock is driven through the vault's generated trait client
+    let strategy = strategy::StrategyTraitClient::new(&env, &strategy_id);
+    assert_eq!(strategy.vault(), contract_id);
+    assert_eq!(strategy.asset(), token_id);
+
+    strategy.deposit(&1000);
+    mock.simulate_price_drift(&1300);
+    assert_eq!(strategy.total_value(), 1300);
+    assert_eq!(strategy.max_withdraw(), 1300);
+
+    // Principal deposited outside the vault is not yield; only the gain is
+    assert_eq!(client.harvest(), 300);
+    assert_eq!(client.get_strategy_debt(&strategy_id), 300);
+
+    mock.simulate_price_drift(&1100);
+    assert_eq!(client.harvest(), -200);
+    assert_eq!(client.get_strategy_debt(&strategy_id), 100);
+    assert_eq!(strategy.harvest(), (0, 0));
+}
+
+#[test]
+fn test_token_holding_strategy_money_flows() {
+    let env = Env::default();
+    env.mock_all_auths_allowing_non_root_auth();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, token_client) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    let strategy_id = env.register(mock_strategy::MockStrategy, ());
+    let strategy = mock_strategy::MockStrategyClient::new(&env, &strategy_id);
+    strategy.initialize(&contract_id, &token_id);
+    client.propose_action(&admin, &ActionType::AddStrategy(strategy_id.clone()));
+
+    let user = Address::generate(&env);
+    stellar_asset_client.mint(&user, &20000);
+    client.deposit(&user, &20000);
+
+    // Rebalancing moves real tokens into the strategy's custody
+    env.ledger().set_timestamp(1000);
+    let mut allocations: Map<Address, i128> = Map::new(&env);
+    allocations.set(strategy_id.clone(), 10000);
+    client.set_oracle_data(&allocations, &env.ledger().timestamp());
+    client.propose_action(&admin, &ActionType::Rebalance(50u32));
+    assert_eq!(token_client.balance(&contract_id), 10000);
+    assert_eq!(token_client.balance(&strategy_id), 10000);
+
+    // One token a second for 100 seconds, paid in by the underlying protocol
+    strategy.set_yield_rate(&1);
+    env.ledger().set_timestamp(1100);
+    stellar_asset_client.mint(&strategy_id, &100);
+    assert_eq!(client.total_assets(), 20100);
+    strategy.set_yield_rate(&0);
+    assert_eq!(client.harvest(), 100);
+
+    // A loss event takes the tokens with it
+    strategy.simulate_loss(&50);
+    assert_eq!(token_client.balance(&strategy_id), 10050);
+    assert_eq!(client.harvest(), -50);
+    assert_eq!(client.total_assets(), 20050);
+
+    // A strategy that reverts blocks withdrawals that need it
+    strategy.set_failure_mode(&mock_strategy::FailureMode {
+        fail_deposit: false,
+        fail_withdraw: true,
+        fail_value: false,
+        short_bps: 0,
+    });
+    assert!(client.try_redeem(&user, &user, &15000).is_err());
+
+    // A strategy that returns 10% short realises a loss the withdrawer bears:
+    // 15000 * (20050 + 1000) / (20000 + 1000) = 15035, of which 5035 is asked
+    // of the strategy and 4532 arrives
+    strategy.set_failure_mode(&mock_strategy::FailureMode {
+        fail_deposit: false,
+        fail_withdraw: false,
+        fail_value: false,
+        short_bps: 1000,
+    });
+    assert_eq!(client.redeem(&user, &user, &15000), 14532);
+    assert_eq!(token_client.balance(&user), 14532);
+    assert_eq!(token_client.balance(&strategy_id), 5518);
+    assert_eq!(strategy.balance(), 5015);
+    assert_eq!(client.total_assets(), 5015);
+}
+
+#[test]
+fn test_strategy_shortfall_on_rebalance_and_removal_is_a_loss() {
+    let env = Env::default();
+    env.mock_all_auths_allowing_non_root_auth();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, token_client) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    let strategy_id = env.register(mock_strategy::MockStrategy, ());
+    let strategy = mock_strategy::MockStrategyClient::new(&env, &strategy_id);
+    strategy.initialize(&contract_id, &token_id);
+    client.propose_action(&admin, &ActionType::AddStrategy(strategy_id.clone()));
+
+    // The strategy carries 15000 of debt backed by 15000 tokens
+    stellar_asset_client.mint(&strategy_id, &15000);
+    strategy.simulate_price_drift(&15000);
+    client.harvest();
+    assert_eq!(client.get_strategy_debt(&strategy_id), 15000);
+
+    // Rebalancing down to 10000 asks for 5000; 20% never arrives
+    strategy.set_failure_mode(&mock_strategy::FailureMode {
+        fail_deposit: false,
+        fail_withdraw: false,
+        fail_value: false,
+        short_bps: 2000,
+    });
+    env.ledger().set_timestamp(1000);
+    let mut allocations: Map<Address, i128> = Map::new(&env);
+    allocations.set(strategy_id.clone(), 10000);
+    client.set_oracle_data(&allocations, &env.ledger().timestamp());
+    client.propose_action(&admin, &ActionType::Rebalance(50u32));
+    assert_eq!(token_client.balance(&contract_id), 4000);
+    assert_eq!(client.get_strategy_debt(&strategy_id), 10000);
+    assert_eq!(client.total_assets(), 14000);
+
+    // Removal only credits what the vault actually received
+    client.remove_strategy(&strategy_id);
+    assert_eq!(token_client.balance(&contract_id), 12000);
+    assert_eq!(client.get_strategy_debt(&strategy_id), 0);
+    assert_eq!(client.total_assets(), 12000);
+}
+
+#[test]
+fn test_rebalance_leaves_reserved_assets_in_the_vault() {
+    let env = Env::default();
+    env.mock_all_auths_allowing_non_root_auth();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, token_client) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    let strategy_id = env.register(mock_strategy::MockStrategy, ());
+    let strategy = mock_strategy::MockStrategyClient::new(&env, &strategy_id);
+    strategy.initialize(&contract_id, &token_id);
+    client.propose_action(&admin, &ActionType::AddStrategy(strategy_id.clone()));
+
+    let user = Address::generate(&env);
+    stellar_asset_client.mint(&user, &2000);
+    client.deposit(&user, &2000);
+    client.set_withdraw_queue_threshold(&500);
+    client.queue_withdraw(&user, &800);
+    client.close_epoch();
+    assert_eq!(client.reserved_assets(), 800);
+
+    // The target asks for more than the vault holds; only the unreserved 1200 moves
+    env.ledger().set_timestamp(1000);
+    let mut allocations: Map<Address, i128> = Map::new(&env);
+    allocations.set(strategy_id.clone(), 10000);
+    client.set_oracle_data(&allocations, &env.ledger().timestamp());
+    client.propose_action(&admin, &ActionType::Rebalance(50u32));
+    assert_eq!(token_client.balance(&strategy_id), 1200);
+    assert_eq!(token_client.balance(&contract_id), 800);
+
+    assert_eq!(client.claim(&user, &1), 800);
+    assert_eq!(token_client.balance(&user), 800);
+}
+
+#[test]
+fn test_strategy_params_cap_rebalance_at_debt_ceiling() {
+    let env = Env::default();
+    env.mock_all_auths_allowing_non_root_auth();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, token_client) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    let strategy_id = env.register(mock_strategy::MockStrategy, ());
+    let strategy = mock_strategy::MockStrategyClient::new(&env, &strategy_id);
+    strategy.initialize(&contract_id, &token_id);
+    client.propose_action(&admin, &ActionType::AddStrategy(strategy_id.clone()));
+
+    let params = client.get_strategy_params(&strategy_id).unwrap();
+    assert_eq!(params.debt_ratio, 10_000);
+    assert_eq!(params.max_debt, i128::MAX);
+    assert_eq!(client.get_strategy_params(&Address::generate(&env)), None);
+
+    assert_eq!(
+        client.try_set_strategy_params(&strategy_id, &10_001u32, &0),
+        Err(Ok(Error::InvalidDebtRatio))
+    );
+    assert_eq!(
+        client.try_set_strategy_params(&Address::generate(&env), &5000u32, &0),
+        Err(Ok(Error::NotInitialized))
+    );
+
+    let user = Address::generate(&env);
+    stellar_asset_client.mint(&user, &16000);
+    client.deposit(&user, &16000);
+
+    // At most 40% of the vault: 6400 of a 10000 target
+    client.set_strategy_params(&strategy_id, &4000u32, &i128::MAX);
+    env.ledger().set_timestamp(1000);
+    let mut allocations: Map<Address, i128> = Map::new(&env);
+    allocations.set(strategy_id.clone(), 10000);
+    client.set_oracle_data(&allocations, &env.ledger().timestamp());
+    client.propose_action(&admin, &ActionType::Rebalance(50u32));
+    assert_eq!(token_client.balance(&strategy_id), 6400);
+    assert_eq!(client.get_strategy_params(&strategy_id).unwrap().current_debt, 6400);
+
+    // A fixed ceiling below the ratio wins; the strategy is not pushed further
+    client.set_strategy_params(&strategy_id, &4000u32, &5000);
+    env.ledger().set_timestamp(1001);
+    client.set_oracle_data(&allocations, &env.ledger().timestamp());
+    client.propose_action(&admin, &ActionType::Rebalance(50u32));
+    assert_eq!(token_client.balance(&strategy_id), 6400);
+
+    // Gains and losses are tracked across reports
+    stellar_asset_client.mint(&strategy_id, &600);
+    strategy.simulate_price_drift(&7000);
+    env.ledger().set_timestamp(2000);
+    client.harvest();
+    strategy.simulate_loss(&200);
+    client.harvest();
+    let params = client.get_strategy_params(&strategy_id).unwrap();
+    assert_eq!(params.debt_ratio, 4000);
+    assert_eq!(params.max_debt, 5000);
+    assert_eq!(params.current_debt, 6800);
+    assert_eq!(params.total_gain, 600);
+    assert_eq!(params.total_loss, 200);
+    assert_eq!(params.last_report, 2000);
+
+    client.remove_strategy(&strategy_id);
+    assert_eq!(client.get_strategy_params(&strategy_id), None);
+}
+
+#[test]
+fn test_reported_profit_unlocks_linearly_and_losses_apply_at_once() {
+    let env = Env::default();
+    env.mock_all_auths_allowing_non_root_auth();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, _) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    let strategy_id = env.register(mock_strategy::MockStrategy, ());
+    let strategy = mock_strategy::MockStrategyClient::new(&env, &strategy_id);
+    client.propose_action(&admin, &ActionType::AddStrategy(strategy_id.clone()));
+
+    let user = Address::generate(&env);
+    stellar_asset_client.mint(&user, &1000);
+    client.deposit(&user, &1000);
+
+    env.ledger().set_timestamp(1000);
+    client.set_profit_unlock_period(&1000);
+    assert_eq!(client.profit_unlock_period(), 1000);
+
+    // Unreported gains are not part of the share price
+    strategy.simulate_price_drift(&500);
+    assert_eq!(client.total_assets(), 1000);
+
+    // Reported profit starts fully locked and is released over the period
+    assert_eq!(client.report(&strategy_id), 500);
+    assert_eq!(client.locked_profit(), 500);
+    assert_eq!(client.total_assets(), 1000);
+    env.ledger().set_timestamp(1500);
+    assert_eq!(client.total_assets(), 1250);
+    env.ledger().set_timestamp(2000);
+    assert_eq!(client.locked_profit(), 0);
+    assert_eq!(client.total_assets(), 1500);
+
+    // A new report locks its own profit on top of what is still locked
+    strategy.simulate_price_drift(&700);
+    client.report(&strategy_id);
+    env.ledger().set_timestamp(2500);
+    assert_eq!(client.total_assets(), 1600);
+
+    // Losses hit the share price before they are even reported
+    strategy.simulate_price_drift(&400);
+    assert_eq!(client.total_assets(), 1300);
+    assert_eq!(client.report(&strategy_id), -300);
+    assert_eq!(client.total_assets(), 1300);
+    assert_eq!(client.get_strategy_params(&strategy_id).unwrap().total_loss, 300);
+
+    assert_eq!(
+        client.try_report(&Address::generate(&env)),
+        Err(Ok(Error::NotInitialized))
+    );
+}
+
+#[test]
+fn test_performance_fee_on_reports_within_one_unlock_period() {
+    let env = Env::default();
+    env.mock_all_auths_allowing_non_root_auth();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, _) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &1000u32, &guardians, &1u32);
+
+    let strategy_id = env.register(mock_strategy::MockStrategy, ());
+    let strategy = mock_strategy::MockStrategyClient::new(&env, &strategy_id);
+    client.propose_action(&admin, &ActionType::AddStrategy(strategy_id.clone()));
+
+    let user = Address::generate(&env);
+    stellar_asset_client.mint(&user, &1000);
+    client.deposit(&user, &1000);
+
+    env.ledger().set_timestamp(1000);
+    client.set_profit_unlock_period(&1000);
+
+    // 10% of each report's profit, although most of the first is still locked
+    strategy.simulate_price_drift(&500);
+    client.report(&strategy_id);
+    assert_eq!(client.accrued_fees(), 50);
+    env.ledger().set_timestamp(1200);
+    strategy.simulate_price_drift(&1000);
+    client.report(&strategy_id);
+    assert_eq!(client.accrued_fees(), 100);
+
+    // Nothing more is charged once the locked profit has been released
+    env.ledger().set_timestamp(3000);
+    assert_eq!(client.locked_profit(), 0);
+    client.report(&strategy_id);
+    assert_eq!(client.accrued_fees(), 100);
+}
+
+#[test]
+fn test_emergency_exit_writes_off_strategy_until_reenabled() {
+    let env = Env::default();
+    env.mock_all_auths_allowing_non_root_auth();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, token_client) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    let strategy_id = env.register(mock_strategy::MockStrategy, ());
+    let strategy = mock_strategy::MockStrategyClient::new(&env, &strategy_id);
+    strategy.initialize(&contract_id, &token_id);
+    client.propose_action(&admin, &ActionType::AddStrategy(strategy_id.clone()));
+
+    let user = Address::generate(&env);
+    stellar_asset_client.mint(&user, &20000);
+    client.deposit(&user, &20000);
+
+    env.ledger().set_timestamp(1000);
+    let mut allocations: Map<Address, i128> = Map::new(&env);
+    allocations.set(strategy_id.clone(), 10000);
+    client.set_oracle_data(&allocations, &env.ledger().timestamp());
+    client.propose_action(&admin, &ActionType::Rebalance(50u32));
+    assert_eq!(token_client.balance(&strategy_id), 10000);
+
+    // Only 60% comes back; the rest is written off against the share price
+    strategy.set_failure_mode(&mock_strategy::FailureMode {
+        fail_deposit: false,
+        fail_withdraw: false,
+        fail_value: false,
+        short_bps: 4000,
+    });
+    client.flag_strategy(&strategy_id, &true);
+    assert_eq!(token_client.balance(&contract_id), 16000);
+    assert_eq!(client.total_assets(), 16000);
+    let params = client.get_strategy_params(&strategy_id).unwrap();
+    assert!(params.emergency_exit);
+    assert_eq!(params.current_debt, 0);
+    assert_eq!(params.total_loss, 4000);
+    assert!(!client.get_strategy_health(&strategy_id).unwrap().is_healthy);
+    assert!(!client.get_target_allocations().contains_key(strategy_id.clone()));
+
+    // Its allocation is set aside while the strategy is exited, even when
+    // the oracle sends it again
+    strategy.set_failure_mode(&mock_strategy::FailureMode::default());
+    env.ledger().set_timestamp(1001);
+    client.set_oracle_data(&allocations, &env.ledger().timestamp());
+    assert!(!client.get_target_allocations().contains_key(strategy_id.clone()));
+    client.propose_action(&admin, &ActionType::Rebalance(50u32));
+    assert_eq!(token_client.balance(&contract_id), 16000);
+    assert_eq!(client.harvest(), 0);
+
+    // The stranded 4000 turns out to be recoverable after all
+    strategy.simulate_price_drift(&4000);
+    assert_eq!(client.total_assets(), 16000);
+
+    // Governance brings it back; the recovery is locked and released like profit
+    client.set_profit_unlock_period(&1000);
+    client.propose_action(&admin, &ActionType::ReenableStrategy(strategy_id.clone()));
+    let params = client.get_strategy_params(&strategy_id).unwrap();
+    assert!(!params.emergency_exit);
+    assert_eq!(params.current_debt, 4000);
+    assert_eq!(params.total_gain, 4000);
+    assert_eq!(client.get_target_allocations().get(strategy_id.clone()), Some(10000));
+    assert_eq!(client.locked_profit(), 4000);
+    assert_eq!(client.total_assets(), 16000);
+    env.ledger().set_timestamp(2001);
+    assert_eq!(client.total_assets(), 20000);
+    assert_eq!(client.harvest(), 0);
+
+    // Its target allocation is restored, so the next rebalance funds it again
+    client.propose_action(&admin, &ActionType::Rebalance(50u32));
+    assert_eq!(token_client.balance(&strategy_id), 10000);
+    assert_eq!(client.get_strategy_debt(&strategy_id), 10000);
+}
+
+#[test]
+fn test_emergency_exit_survives_a_reverting_strategy() {
+    let env = Env::default();
+    env.mock_all_auths_allowing_non_root_auth();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, _) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    let strategy_id = env.register(mock_strategy::MockStrategy, ());
+    let strategy = mock_strategy::MockStrategyClient::new(&env, &strategy_id);
+    strategy.initialize(&contract_id, &token_id);
+    client.propose_action(&admin, &ActionType::AddStrategy(strategy_id.clone()));
+
+    stellar_asset_client.mint(&contract_id, &1000);
+    stellar_asset_client.mint(&strategy_id, &3000);
+    strategy.simulate_price_drift(&3000);
+    client.harvest();
+    assert_eq!(client.total_assets(), 4000);
+
+    strategy.set_failure_mode(&mock_strategy::FailureMode {
+        fail_deposit: false,
+        fail_withdraw: true,
+        fail_value: false,
+        short_bps: 0,
+    });
+    client.flag_strategy(&strategy_id, &true);
+    assert_eq!(client.total_assets(), 1000);
+    assert_eq!(client.get_strategy_params(&strategy_id).unwrap().total_loss, 3000);
+}
+
+#[test]
+fn test_emergency_exit_survives_a_strategy_that_cannot_value_itself() {
+    let env = Env::default();
+    env.mock_all_auths_allowing_non_root_auth();
+
+    let token_admin = Address::generate(&env);
+    let (token_id, stellar_asset_client, token_client) = create_token_contract(&env, &token_admin);
+
+    let contract_id = env.register(VolatilityShield, ());
+    let client = VolatilityShieldClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let oracle = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let guardians = soroban_sdk::vec![&env, admin.clone()];
+    client.init(&admin, &token_id, &oracle, &treasury, &0u32, &guardians, &1u32);
+
+    let strategy_id = env.register(mock_strategy::MockStrategy, ());
+    let strategy = mock_strategy::MockStrategyClient::new(&env, &strategy_id);
+    strategy.initialize(&contract_id, &token_id);
+    client.propose_action(&admin, &ActionType::AddStrategy(strategy_id.clone()));
+
+    stellar_asset_client.mint(&contract_id, &1000);
+    stellar_asset_client.mint(&strategy_id, &3000);
+    strategy.simulate_price_drift(&3000);
+    client.harvest();
+
+    // total_value reverts, but withdrawals still go through
+    strategy.set_failure_mode(&mock_strategy::FailureMode {
+        fail_deposit: false,
+        fail_withdraw: false,
+        fail_value: true,
+        short_bps: 0,
+    });
+    client.flag_strategy(&strategy_id, &true);
+    assert_eq!(token_client.balance(&contract_id), 4000);
+    assert_eq!(client.total_assets(), 4000);
+    let params = client.get_strategy_params(&strategy_id).unwrap();
+    assert_eq!(params.current_debt, 0);
+    assert_eq!(params.total_loss, 0);
+
+    // A second such strategy that is still live is reported as unhealthy
+    let broken_id = env.register(mock_strategy::MockStrategy, ());
+    let broken = mock_strategy::MockStrategyClient::new(&env, &broken_id);
+    broken.initialize(&contract_id, &token_id);
+    client.propose_action(&admin, &ActionType::AddStrategy(broken_id.clone()));
+    broken.set_failure_mode(&mock_strategy::FailureMode {
+        fail_deposit: false,
+        fail_withdraw: false,
+        fail_value: true,
+        short_bps: 0,
+    });
+    let unhealthy = client.check_strategy_health();
+    assert_eq!(unhealthy.len(), 1);
+    assert_eq!(unhealthy.get(0).unwrap(), broken_id);
+
+    // Both can still be removed
+    client.remove_strategy(&broken_id);
+    client.remove_strategy(&strategy_id);
+    assert_eq!(client.total_assets(), 4000);
+}
+